@@ -0,0 +1,14 @@
+CREATE TABLE sessions (
+                          id            UUID PRIMARY KEY,
+                          user_id       UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                          provider      TEXT NOT NULL,
+                          access_token  BYTEA NOT NULL,
+                          refresh_token BYTEA,
+                          expires_at    TIMESTAMPTZ NOT NULL,
+                          revoked_at    TIMESTAMPTZ,
+                          created_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+                          updated_at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
+CREATE INDEX sessions_expires_at_idx ON sessions (expires_at);
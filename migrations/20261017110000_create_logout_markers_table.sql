@@ -0,0 +1,16 @@
+ALTER TABLE sessions ADD COLUMN idp_sid TEXT;
+
+CREATE INDEX sessions_provider_idp_sid_idx ON sessions (provider, idp_sid);
+
+CREATE TABLE logout_markers (
+                                id            UUID PRIMARY KEY,
+                                issuer        TEXT NOT NULL,
+                                subject       TEXT,
+                                sid           TEXT,
+                                logged_out_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                                expires_at    TIMESTAMPTZ NOT NULL,
+                                CONSTRAINT logout_markers_target CHECK (subject IS NOT NULL OR sid IS NOT NULL)
+);
+
+CREATE INDEX logout_markers_subject_idx ON logout_markers (issuer, subject);
+CREATE INDEX logout_markers_sid_idx ON logout_markers (issuer, sid);
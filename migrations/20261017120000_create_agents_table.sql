@@ -0,0 +1,11 @@
+CREATE TABLE agents (
+                        id           UUID PRIMARY KEY,
+                        idp_issuer   TEXT NOT NULL,
+                        idp_subject  TEXT NOT NULL,
+                        name         TEXT NOT NULL,
+                        client_id    TEXT,
+                        last_seen_at TIMESTAMPTZ,
+                        created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+                        updated_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+                        CONSTRAINT agents_idp_identity_unique UNIQUE (idp_issuer, idp_subject)
+);
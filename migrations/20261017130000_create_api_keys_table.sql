@@ -0,0 +1,15 @@
+CREATE TABLE api_keys (
+                          id           UUID PRIMARY KEY,
+                          user_id      UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                          name         TEXT NOT NULL,
+                          prefix       TEXT NOT NULL UNIQUE,
+                          key_hash     TEXT NOT NULL,
+                          scopes       TEXT[] NOT NULL DEFAULT '{}',
+                          expires_at   TIMESTAMPTZ,
+                          last_used_at TIMESTAMPTZ,
+                          revoked_at   TIMESTAMPTZ,
+                          created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+                          updated_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
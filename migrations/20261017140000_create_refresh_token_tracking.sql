@@ -0,0 +1,30 @@
+CREATE TABLE refresh_token_families (
+                                        id            UUID PRIMARY KEY,
+                                        provider      TEXT NOT NULL,
+                                        current_token BYTEA,
+                                        revoked_at    TIMESTAMPTZ,
+                                        created_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+                                        updated_at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX refresh_token_families_updated_at_idx ON refresh_token_families (updated_at);
+
+CREATE TABLE refresh_tokens (
+                                token_hash TEXT PRIMARY KEY,
+                                family_id  UUID NOT NULL REFERENCES refresh_token_families(id) ON DELETE CASCADE,
+                                rotated_at TIMESTAMPTZ,
+                                created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX refresh_tokens_family_id_idx ON refresh_tokens (family_id);
+
+CREATE TABLE security_events (
+                                 id         UUID PRIMARY KEY,
+                                 kind       TEXT NOT NULL,
+                                 provider   TEXT,
+                                 subject    TEXT,
+                                 details    JSONB NOT NULL DEFAULT '{}',
+                                 created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX security_events_created_at_idx ON security_events (created_at);
@@ -0,0 +1,8 @@
+ALTER TABLE users
+    ADD COLUMN name           TEXT,
+    ADD COLUMN given_name     TEXT,
+    ADD COLUMN family_name    TEXT,
+    ADD COLUMN picture        TEXT,
+    ADD COLUMN locale         TEXT,
+    ADD COLUMN email_verified BOOLEAN,
+    ADD COLUMN org_id         TEXT;
@@ -0,0 +1,7 @@
+CREATE TABLE oidc_metadata_snapshots (
+                                         issuer_url TEXT PRIMARY KEY,
+                                         discovery  JSONB NOT NULL,
+                                         jwks       JSONB NOT NULL,
+                                         fetched_at TIMESTAMPTZ NOT NULL,
+                                         expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,9 @@
+-- The login a family belongs to, so a back-channel logout can revoke it.
+-- Families created before this column existed have no login and are refused.
+ALTER TABLE refresh_token_families
+    ADD COLUMN issuer  TEXT,
+    ADD COLUMN subject TEXT,
+    ADD COLUMN sid     TEXT;
+
+CREATE INDEX refresh_token_families_subject_idx ON refresh_token_families (issuer, subject);
+CREATE INDEX refresh_token_families_sid_idx ON refresh_token_families (issuer, sid);
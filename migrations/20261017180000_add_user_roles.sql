@@ -0,0 +1,4 @@
+-- Roles of the user's latest login, so API keys never carry more than the owner still holds.
+-- NULL until the user signs in again.
+ALTER TABLE users
+    ADD COLUMN roles TEXT[];
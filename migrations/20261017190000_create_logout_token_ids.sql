@@ -0,0 +1,8 @@
+-- `jti`s of processed back-channel logout tokens, kept until the token expires, so a
+-- replayed token is rejected.
+CREATE TABLE logout_token_ids (
+    issuer     TEXT NOT NULL,
+    jti        TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (issuer, jti)
+);
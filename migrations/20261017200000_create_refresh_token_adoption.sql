@@ -0,0 +1,7 @@
+-- Refresh tokens issued before families recorded their login are adopted into a family
+-- on first use, for one refresh token lifetime after this migration ran.
+CREATE TABLE refresh_token_adoption (
+    started_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+INSERT INTO refresh_token_adoption DEFAULT VALUES;
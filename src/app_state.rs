@@ -4,14 +4,19 @@ use moka::future::Cache;
 use sqlx::PgPool;
 use axum::extract::FromRef;
 use reqwest::Client;
-use tokio::sync::Mutex;
-use crate::infrastructure::config::Config;
-use crate::domain::entities::User;
-use crate::infrastructure::persistence::{PgUserRepo, UserRepository};
+use crate::infrastructure::config::{Config, SessionMode};
+use crate::domain::entities::{Agent, User};
+use crate::infrastructure::crypto::{TokenCipher, ValueSigner};
+use crate::infrastructure::persistence::{AgentRepository, ApiKeyRepository, PgAgentRepo, PgApiKeyRepo, LogoutMarkerRepository, PgLogoutMarkerRepo, PgRefreshTokenRepo, PgSecurityEventRepo, PgSessionRepo, PgUserRepo, RefreshTokenRepository, SecurityEventRepository, SessionRepository, UserRepository};
+use crate::application::agent_service::AgentService;
+use crate::application::api_key_service::ApiKeyService;
 use crate::application::auth_service::AuthService;
-use crate::application::user_service::UserService;
-use crate::infrastructure::oidc::provider::OidcProvider;
-use crate::infrastructure::oidc::provider::OidcAdminApi;
+use crate::application::logout_service::LogoutService;
+use crate::application::refresh_token_service::RefreshTokenService;
+use crate::application::session_service::SessionService;
+use crate::application::user_service::{ManagedIssuer, UserService};
+use crate::infrastructure::oidc::dpop::DpopVerifier;
+use crate::infrastructure::oidc::registry::OidcProviderRegistry;
 
 #[derive(Clone, FromRef)]
 pub struct AppState {
@@ -19,11 +24,18 @@ pub struct AppState {
     pub db: Arc<PgPool>,
     pub auth_service: Arc<AuthService>,
     pub user_service: Arc<UserService>,
+    pub agent_service: Arc<AgentService>,
+    pub api_key_service: Arc<ApiKeyService>,
+    /// Present only in server-side session mode (`SESSION_MODE=server`)
+    pub session_service: Option<Arc<SessionService>>,
+    pub logout_service: Arc<LogoutService>,
+    pub refresh_token_service: Arc<RefreshTokenService>,
+    pub dpop_verifier: Arc<DpopVerifier>,
     pub http_client: Client,
 }
 
 impl AppState {
-    pub fn new(cfg: Config, pool: PgPool, provider: Arc<dyn OidcProvider + Send + Sync>, http_client: Client, management_client: Option<Arc<Mutex<dyn OidcAdminApi + Send + Sync>>>) -> Self {
+    pub fn new(cfg: Config, pool: PgPool, providers: Arc<OidcProviderRegistry>, http_client: Client, management_clients: Vec<ManagedIssuer>) -> anyhow::Result<Self> {
         let config = cfg.clone();
         let db = Arc::new(pool);
 
@@ -36,9 +48,65 @@ impl AppState {
 
         let user_repository: Arc<dyn UserRepository> = Arc::new(PgUserRepo::new(db.clone()));
 
-        let user_service = Arc::new(UserService::new(user_repository, cache, management_client, cfg.issuer_url.clone()));
-        let auth_service = Arc::new(AuthService::new(provider, user_service.clone()));
+        let agent_cache: Cache<String, Arc<Agent>> = Cache::builder()
+            .time_to_live(Duration::from_secs(600))
+            .max_capacity(10_000)
+            .build();
+        let agent_repository: Arc<dyn AgentRepository> = Arc::new(PgAgentRepo::new(db.clone()));
+        let agent_service = Arc::new(AgentService::new(agent_repository, agent_cache));
+
+        let api_key_repository: Arc<dyn ApiKeyRepository> = Arc::new(PgApiKeyRepo::new(db.clone()));
+        let api_key_service = Arc::new(ApiKeyService::new(api_key_repository, user_repository.clone()));
+
+        let user_service = Arc::new(UserService::new(user_repository, cache, agent_service.clone(), management_clients));
+
+        // The family's latest token is only kept (encrypted) when a key is configured,
+        // so it can be revoked at the provider when reuse is detected
+        let refresh_token_repository: Arc<dyn RefreshTokenRepository> = Arc::new(PgRefreshTokenRepo::new(db.clone()));
+        let security_event_repository: Arc<dyn SecurityEventRepository> = Arc::new(PgSecurityEventRepo::new(db.clone()));
+        let refresh_token_service = Arc::new(RefreshTokenService::new(
+            refresh_token_repository,
+            security_event_repository,
+            config.session_encryption_key.as_deref().map(TokenCipher::from_base64).transpose()?,
+            time::Duration::seconds(config.session_ttl_secs as i64),
+        ));
+
+        let auth_service = Arc::new(AuthService::new(
+            providers,
+            user_service.clone(),
+            refresh_token_service.clone(),
+            config.downstream_services.clone(),
+        ));
+
+        let session_service = match config.session_mode {
+            SessionMode::Cookie => None,
+            SessionMode::Server => {
+                let key = config.session_encryption_key.as_deref()
+                    .ok_or_else(|| anyhow::anyhow!("SESSION_ENCRYPTION_KEY is required in server session mode"))?;
+                let secret = config.session_secret.as_deref()
+                    .ok_or_else(|| anyhow::anyhow!("SESSION_SECRET is required in server session mode"))?;
+
+                let session_repository: Arc<dyn SessionRepository> = Arc::new(PgSessionRepo::new(db.clone()));
+                Some(Arc::new(SessionService::new(
+                    session_repository,
+                    TokenCipher::from_base64(key)?,
+                    ValueSigner::new(secret.as_bytes()),
+                    time::Duration::seconds(config.session_ttl_secs as i64),
+                )))
+            }
+        };
+
+        let logout_repository: Arc<dyn LogoutMarkerRepository> = Arc::new(PgLogoutMarkerRepo::new(db.clone()));
+        let logout_service = Arc::new(LogoutService::new(
+            logout_repository,
+            user_service.clone(),
+            session_service.clone(),
+            refresh_token_service.clone(),
+            time::Duration::seconds(config.session_ttl_secs as i64),
+        ));
+
+        let dpop_verifier = Arc::new(DpopVerifier::new(config.dpop_proof_max_age_secs));
 
-        AppState { config, db, auth_service, user_service, http_client }
+        Ok(AppState { config, db, auth_service, user_service, agent_service, api_key_service, session_service, logout_service, refresh_token_service, dpop_verifier, http_client })
     }
 }
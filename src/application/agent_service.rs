@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use moka::future::Cache;
+use sqlx::Error;
+use crate::domain::entities::Agent;
+use crate::domain::repositories::AgentRepository;
+use crate::infrastructure::oidc::{OidcClaims, OidcError};
+
+fn id_key(issuer: &str, subject: &str) -> String {
+    format!("{}::{}", issuer, subject)
+}
+
+/// Identity records of machine principals (agents), the counterpart of `UserService`.
+#[derive(Clone)]
+pub struct AgentService {
+    pub agent_repository: Arc<dyn AgentRepository>,
+    pub cache: Cache<String, Arc<Agent>>,
+}
+
+impl AgentService {
+    pub fn new(agent_repository: Arc<dyn AgentRepository>, cache: Cache<String, Arc<Agent>>) -> Self {
+        Self { agent_repository, cache }
+    }
+
+    pub async fn get_agent_by_identity(&self, issuer: &str, subject: &str) -> Result<Option<Arc<Agent>>, Error> {
+        let key = id_key(issuer, subject);
+        if let Some(agent) = self.cache.get(&key).await {
+            return Ok(Some(agent));
+        }
+
+        let maybe_agent = self.agent_repository.find_by_subject(issuer, subject).await?;
+        if let Some(a) = maybe_agent {
+            let arc_agent = Arc::new(a);
+            self.cache.insert(key, arc_agent.clone()).await;
+            return Ok(Some(arc_agent));
+        }
+        Ok(None)
+    }
+
+    /// Record the agent behind a machine token. Cached, so `last_seen_at` is
+    /// refreshed at most once per cache lifetime.
+    pub async fn sync_agent_from_claims(&self, claims: &OidcClaims) -> Result<Arc<Agent>, OidcError> {
+        if let Some(agent) = self.cache.get(&id_key(&claims.iss, &claims.sub)).await {
+            return Ok(agent);
+        }
+
+        self.upsert_and_cache_agent(&claims.iss, &claims.sub, &claims.agent_name(), claims.client_id.as_deref(), true)
+            .await
+            .map_err(|e| OidcError::Provider(format!("Agent upsert failed: {}", e)))
+    }
+
+    pub async fn upsert_and_cache_agent(
+        &self,
+        issuer: &str,
+        subject: &str,
+        name: &str,
+        client_id: Option<&str>,
+        seen: bool,
+    ) -> Result<Arc<Agent>, Error> {
+        let agent = self.agent_repository.upsert_agent(issuer, subject, name, client_id, seen).await?;
+        let key = id_key(&agent.idp_issuer, &agent.idp_subject);
+        let arc_agent = Arc::new(agent);
+        self.cache.insert(key, arc_agent.clone()).await;
+        Ok(arc_agent)
+    }
+}
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+use base64::{engine::general_purpose, Engine as _};
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use crate::domain::entities::ApiKey;
+use crate::domain::repositories::{ApiKeyRepository, UserRepository};
+use crate::infrastructure::oidc::{OidcClaims, PrincipalKind};
+use crate::shared::errors::ServiceError;
+
+/// `aud` of claims produced from an API key, so handlers can tell them from OIDC tokens.
+pub const API_KEY_AUDIENCE: &str = "urn:hestix:api-key";
+
+/// Presented keys look like `hx_<prefix>_<secret>`.
+const KEY_PREFIX: &str = "hx_";
+
+/// Lifetime of claims built from a key without expiry, in seconds.
+const CLAIMS_TTL_SECS: u64 = 3600;
+
+/// A freshly created key. `secret` is the full key and is only ever shown once.
+pub struct CreatedApiKey {
+    pub key: ApiKey,
+    pub secret: String,
+}
+
+/// Personal API keys for scripts that can't run an OIDC flow. A key never grants more than
+/// its owner's roles at their latest login; keys are deleted with their owner and otherwise
+/// only revoked by the owner.
+pub struct ApiKeyService {
+    repository: Arc<dyn ApiKeyRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    rng: SystemRandom,
+}
+
+impl ApiKeyService {
+    pub fn new(repository: Arc<dyn ApiKeyRepository>, user_repository: Arc<dyn UserRepository>) -> Self {
+        Self { repository, user_repository, rng: SystemRandom::new() }
+    }
+
+    /// Create a key for `user_id`. `scopes` must be a subset of `granted_roles`,
+    /// the roles of the token the key is created with, which are recorded as the owner's roles.
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        scopes: Vec<String>,
+        granted_roles: &[String],
+        expires_at: Option<OffsetDateTime>,
+    ) -> Result<CreatedApiKey, ServiceError> {
+        let name = validate_name(name)?;
+
+        if let Some(scope) = scopes.iter().find(|s| !granted_roles.contains(s)) {
+            return Err(ServiceError::Validation(format!("scope not granted to you: {scope}")));
+        }
+        if expires_at.is_some_and(|t| t <= OffsetDateTime::now_utc()) {
+            return Err(ServiceError::Validation("expiry must be in the future".into()));
+        }
+
+        // Keys are checked against the owner's recorded roles; make sure they are known and current
+        self.user_repository.update_roles(user_id, granted_roles).await?;
+
+        let prefix = hex(&self.random_bytes::<8>()?);
+        let secret = general_purpose::URL_SAFE_NO_PAD.encode(self.random_bytes::<32>()?);
+        let now = OffsetDateTime::now_utc();
+
+        let key = self.repository.create(&ApiKey {
+            id: Uuid::new_v4(),
+            user_id,
+            name,
+            prefix: prefix.clone(),
+            key_hash: hash_secret(&secret),
+            scopes,
+            expires_at,
+            last_used_at: None,
+            revoked_at: None,
+            created_at: now,
+            updated_at: now,
+        }).await?;
+
+        tracing::info!(key_id = %key.id, %user_id, "API key created");
+        Ok(CreatedApiKey { key, secret: format!("{KEY_PREFIX}{prefix}_{secret}") })
+    }
+
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<ApiKey>, ServiceError> {
+        Ok(self.repository.list_for_user(user_id).await?)
+    }
+
+    pub async fn rename(&self, user_id: Uuid, id: Uuid, name: &str) -> Result<ApiKey, ServiceError> {
+        let name = validate_name(name)?;
+        self.repository.rename(user_id, id, &name).await?
+            .ok_or_else(|| ServiceError::NotFound("API key not found".into()))
+    }
+
+    pub async fn revoke(&self, user_id: Uuid, id: Uuid) -> Result<(), ServiceError> {
+        if !self.repository.revoke(user_id, id).await? {
+            return Err(ServiceError::NotFound("API key not found".into()));
+        }
+        tracing::info!(key_id = %id, %user_id, "API key revoked");
+        Ok(())
+    }
+
+    /// Check a presented key and build claims for its owner, limited to the key's scopes
+    /// that the owner still holds.
+    pub async fn authenticate(&self, presented: &str) -> Result<OidcClaims, ServiceError> {
+        let invalid = || ServiceError::Authentication("invalid API key".into());
+
+        let (prefix, secret) = parse_key(presented).ok_or_else(invalid)?;
+
+        let key = self.repository.find_active_by_prefix(prefix).await?.ok_or_else(invalid)?;
+        if !secret_matches(secret, &key.key_hash) {
+            return Err(invalid());
+        }
+
+        let user = self.user_repository.find_by_id(key.user_id).await?.ok_or_else(invalid)?;
+        let roles = effective_roles(&key.scopes, user.roles.as_deref())?;
+
+        if let Err(e) = self.repository.touch_last_used(key.id).await {
+            tracing::warn!(key_id = %key.id, "Failed to record API key use: {}", e);
+        }
+
+        let now = OffsetDateTime::now_utc().unix_timestamp().max(0) as u64;
+        let exp = key.expires_at
+            .map(|t| t.unix_timestamp().max(0) as u64)
+            .unwrap_or(now + CLAIMS_TTL_SECS);
+
+        Ok(OidcClaims {
+            exp,
+            iat: now,
+            iss: user.idp_issuer.clone(),
+            aud: API_KEY_AUDIENCE.to_string(),
+            sub: user.idp_subject.clone(),
+            email: Some(user.email.clone()),
+            preferred_username: Some(user.username.clone()),
+            name: user.name.clone(),
+            given_name: user.given_name.clone(),
+            family_name: user.family_name.clone(),
+            picture: user.picture.clone(),
+            locale: user.locale.clone(),
+            email_verified: user.email_verified,
+            org_id: user.org_id.clone(),
+            sid: None,
+            client_id: None,
+            principal: PrincipalKind::Human,
+            // No interactive authentication behind a key, so step-up requirements never pass
+            acr: None,
+            amr: Vec::new(),
+            auth_time: None,
+            cnf_jkt: None,
+            roles,
+            raw: serde_json::Map::new(),
+        })
+    }
+
+    fn random_bytes<const N: usize>(&self) -> Result<[u8; N], ServiceError> {
+        let mut bytes = [0u8; N];
+        self.rng.fill(&mut bytes)
+            .map_err(|_| ServiceError::Internal("OS RNG failed".into()))?;
+        Ok(bytes)
+    }
+}
+
+/// Split `hx_<prefix>_<secret>` into prefix and secret.
+fn parse_key(presented: &str) -> Option<(&str, &str)> {
+    let (prefix, secret) = presented.strip_prefix(KEY_PREFIX)?.split_once('_')?;
+    (!prefix.is_empty() && !secret.is_empty()).then_some((prefix, secret))
+}
+
+fn secret_matches(secret: &str, key_hash: &str) -> bool {
+    constant_time_eq(hash_secret(secret).as_bytes(), key_hash.as_bytes())
+}
+
+/// The key's scopes the owner still holds. Roles the owner lost since the key was created are
+/// dropped; keys of an owner whose roles were never recorded are refused until they sign in.
+fn effective_roles(scopes: &[String], owner_roles: Option<&[String]>) -> Result<Vec<String>, ServiceError> {
+    let owner_roles = owner_roles.ok_or_else(|| {
+        ServiceError::Authentication("API key owner's roles are unknown; the owner must sign in again".into())
+    })?;
+    Ok(scopes.iter().filter(|scope| owner_roles.contains(scope)).cloned().collect())
+}
+
+fn validate_name(name: &str) -> Result<String, ServiceError> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(ServiceError::Validation("name must be 1 to 100 characters".into()));
+    }
+    Ok(name.to_string())
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roles(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_prefix_and_secret() {
+        assert_eq!(parse_key("hx_0123abcd_s3cr_et"), Some(("0123abcd", "s3cr_et")));
+        assert_eq!(parse_key("0123abcd_secret"), None);
+        assert_eq!(parse_key("hx_0123abcd"), None);
+        assert_eq!(parse_key("hx__secret"), None);
+        assert_eq!(parse_key("hx_0123abcd_"), None);
+        assert_eq!(parse_key("Bearer hx_0123abcd_secret"), None);
+    }
+
+    #[test]
+    fn compares_the_secret_by_hash() {
+        let stored = hash_secret("secret");
+        assert!(secret_matches("secret", &stored));
+        assert!(!secret_matches("secreT", &stored));
+        assert!(!secret_matches("", &stored));
+        // The stored hash itself is not a valid secret
+        assert!(!secret_matches(&stored, &stored));
+    }
+
+    #[test]
+    fn constant_time_eq_needs_equal_length() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+
+    #[test]
+    fn scopes_are_limited_to_the_owner_roles() {
+        let owner = roles(&["user", "admin"]);
+        assert_eq!(effective_roles(&roles(&["user", "admin"]), Some(&owner)).unwrap(), roles(&["user", "admin"]));
+        assert_eq!(effective_roles(&roles(&["admin", "billing"]), Some(&owner)).unwrap(), roles(&["admin"]));
+        assert!(effective_roles(&roles(&["admin"]), Some(&[])).unwrap().is_empty());
+        assert!(effective_roles(&[], Some(&owner)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unknown_owner_roles_are_an_error() {
+        assert!(matches!(
+            effective_roles(&roles(&["user"]), None),
+            Err(ServiceError::Authentication(_))
+        ));
+    }
+}
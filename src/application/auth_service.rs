@@ -1,69 +1,344 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use moka::Expiry;
+use moka::future::Cache;
+use crate::application::dto::auth::authorization_request::AuthorizationRequest;
+use crate::application::dto::auth::device_authorization::{DeviceAuthorizationResponse, DeviceTokenPoll};
 use crate::application::dto::auth::token_response::TokenResponse;
+use crate::application::refresh_token_service::{RefreshTokenService, RefreshTokenStatus};
 use crate::application::user_service::UserService;
-use crate::infrastructure::oidc::{OidcClaims};
-use crate::infrastructure::oidc::provider::OidcProvider;
+use crate::domain::entities::User;
+use crate::infrastructure::oidc::{OidcClaims, TokenTypeHint};
 use crate::infrastructure::oidc::error::OidcError;
+use crate::infrastructure::oidc::introspection::token_cache_key;
+use crate::infrastructure::oidc::jwk::looks_like_jwt;
+use crate::infrastructure::oidc::registry::{OidcProviderRegistry, RegisteredProvider};
+
+/// Result of a successful login (code exchange or device grant): the provider's
+/// tokens, their validated claims and the synced local user.
+pub struct AuthenticatedTokens {
+    pub tokens: TokenResponse,
+    pub claims: OidcClaims,
+    pub user: Arc<User>,
+}
+
+/// How long a refresh result is shared with requests presenting the same refresh token.
+const REFRESH_RESULT_TTL: Duration = Duration::from_secs(30);
+
+/// How long an opaque token that no provider accepted is rejected without asking again.
+const REJECTED_OPAQUE_TTL: Duration = Duration::from_secs(30);
+
+/// Exchanged tokens are dropped this long before they expire, so callers never get a token
+/// that runs out in flight.
+const EXCHANGED_TOKEN_MARGIN: Duration = Duration::from_secs(30);
+
+/// Lifetime assumed for exchanged tokens without `expires_in`.
+const EXCHANGED_TOKEN_DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// An exchanged token together with the expiry (unix time) of the token it was exchanged for.
+#[derive(Clone)]
+struct ExchangedToken {
+    tokens: TokenResponse,
+    subject_exp: u64,
+}
+
+/// Expire cached exchanged tokens with the token itself, and never after the subject token.
+struct UntilExchangedTokenExpiry;
+
+impl Expiry<String, ExchangedToken> for UntilExchangedTokenExpiry {
+    fn expire_after_create(&self, _key: &String, value: &ExchangedToken, _created_at: Instant) -> Option<Duration> {
+        let ttl = value.tokens.expires_in
+            .map(|secs| Duration::from_secs(secs.max(0) as u64))
+            .unwrap_or(EXCHANGED_TOKEN_DEFAULT_TTL);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let subject_ttl = Duration::from_secs(value.subject_exp.saturating_sub(now));
+        Some(ttl.min(subject_ttl).saturating_sub(EXCHANGED_TOKEN_MARGIN))
+    }
+}
 
 #[derive(Clone)]
 pub struct AuthService {
-    pub provider: Arc<dyn OidcProvider + Send + Sync>,
+    pub providers: Arc<OidcProviderRegistry>,
     user_service: Arc<UserService>,
+    refresh_tokens: Arc<RefreshTokenService>,
+    /// Recent refresh results keyed by a hash of provider and refresh token
+    refreshes: Cache<String, TokenResponse>,
+    /// Downstream service name -> audience requested in token exchange
+    downstream_services: Arc<HashMap<String, String>>,
+    /// Exchanged tokens keyed by a hash of the subject token and the audience
+    exchanged: Cache<String, ExchangedToken>,
+    /// Opaque tokens recently rejected by every introspecting provider, keyed by `token_cache_key`
+    rejected_opaque: Cache<String, ()>,
 }
 
 impl AuthService {
-    pub fn new(provider: Arc<dyn OidcProvider + Send + Sync>, user_service: Arc<UserService>) -> Self {
-        Self { provider, user_service }
+    pub fn new(
+        providers: Arc<OidcProviderRegistry>,
+        user_service: Arc<UserService>,
+        refresh_tokens: Arc<RefreshTokenService>,
+        downstream_services: HashMap<String, String>,
+    ) -> Self {
+        let refreshes = Cache::builder()
+            .time_to_live(REFRESH_RESULT_TTL)
+            .max_capacity(10_000)
+            .build();
+
+        let exchanged = Cache::builder()
+            .max_capacity(10_000)
+            .expire_after(UntilExchangedTokenExpiry)
+            .build();
+
+        let rejected_opaque = Cache::builder()
+            .time_to_live(REJECTED_OPAQUE_TTL)
+            .max_capacity(10_000)
+            .build();
+
+        Self {
+            providers,
+            user_service,
+            refresh_tokens,
+            refreshes,
+            downstream_services: Arc::new(downstream_services),
+            exchanged,
+            rejected_opaque,
+        }
     }
 
+    /// `provider` selects a configured provider by name; `None` uses the default one.
+    /// `nonce` is the value sent in the authorization request; the ID token must echo it.
     pub async fn exchange_code_for_token(
         &self,
+        provider: Option<&str>,
         code: String,
         code_verifier: Option<String>,
-    ) -> Result<TokenResponse, OidcError> {
-        let tokens = self.provider.exchange_code_for_tokens(&code, code_verifier.as_deref()).await?;
+        nonce: Option<String>,
+    ) -> Result<AuthenticatedTokens, OidcError> {
+        let registered = self.providers.get(provider)?;
+        let tokens = registered.provider.exchange_code_for_tokens(&code, code_verifier.as_deref()).await?;
+
+        self.authenticate_tokens(registered, tokens, nonce.as_deref()).await
+    }
+
+    /// Start a device login (RFC 8628) for TVs, consoles and CLI tools.
+    pub async fn start_device_login(&self, provider: Option<&str>) -> Result<DeviceAuthorizationResponse, OidcError> {
+        self.providers.get(provider)?.provider.start_device_authorization().await
+    }
+
+    /// Poll a device login once. Once the user approved, the tokens are validated
+    /// and the user synced exactly like after a code exchange.
+    pub async fn poll_device_login(
+        &self,
+        provider: Option<&str>,
+        device_code: &str,
+    ) -> Result<DeviceTokenPoll<AuthenticatedTokens>, OidcError> {
+        let registered = self.providers.get(provider)?;
+
+        Ok(match registered.provider.poll_device_token(device_code).await? {
+            DeviceTokenPoll::Pending => DeviceTokenPoll::Pending,
+            DeviceTokenPoll::SlowDown => DeviceTokenPoll::SlowDown,
+            DeviceTokenPoll::Denied => DeviceTokenPoll::Denied,
+            DeviceTokenPoll::Expired => DeviceTokenPoll::Expired,
+            DeviceTokenPoll::InvalidGrant => DeviceTokenPoll::InvalidGrant,
+            DeviceTokenPoll::Complete(tokens) => {
+                DeviceTokenPoll::Complete(self.authenticate_tokens(registered, tokens, None).await?)
+            }
+        })
+    }
+
+    /// Validate freshly issued tokens, enrich the claims from the ID token, sync the user
+    /// and start tracking the refresh token's rotation.
+    async fn authenticate_tokens(
+        &self,
+        registered: &RegisteredProvider,
+        tokens: TokenResponse,
+        expected_nonce: Option<&str>,
+    ) -> Result<AuthenticatedTokens, OidcError> {
+        let provider = registered.provider.as_ref();
 
         // Always validate access token to get roles + base checks
-        let mut access_claims = self.provider.validate_access_token(&tokens.access_token).await?;
-
-        // If we received an id_token and the toggle is enabled, validate and enrich
-        if let Some(idt) = &tokens.id_token {
-            if let Ok(id_claims) = self.provider.validate_id_token(idt).await {
-                // overwrite/enrich user-facing fields from ID token
-                if access_claims.email.is_none() {
-                    access_claims.email = id_claims.email;
+        let mut access_claims = provider.validate_access_token(&tokens.access_token).await?;
+
+        let id_claims = match (&tokens.id_token, expected_nonce) {
+            // A nonce was sent: the ID token must be valid and echo it before any claim is used
+            (Some(idt), Some(nonce)) => {
+                let id_claims = provider.validate_id_token(idt).await?;
+                if id_claims.raw.get("nonce").and_then(|v| v.as_str()) != Some(nonce) {
+                    return Err(OidcError::InvalidClaim("nonce", "nonce mismatch".into()));
                 }
-                if access_claims.preferred_username.is_none() {
-                    access_claims.preferred_username = id_claims.preferred_username;
+                Some(id_claims)
+            }
+            (Some(idt), None) => provider.validate_id_token(idt).await.ok(),
+            (None, _) => None,
+        };
+
+        // If we received an id_token, enrich the user-facing fields from it
+        if let Some(id_claims) = id_claims {
+            access_claims = access_claims.merge_profile(&id_claims.raw);
+            access_claims.merge_authentication_context(&id_claims);
+            // Note: keep roles from access token; ID token typically doesn't carry them
+        }
+
+        // Fall back to the userinfo endpoint when the email (required for users) is still
+        // missing, or when there was no ID token to take the profile from
+        if !access_claims.is_machine() && (access_claims.email.is_none() || tokens.id_token.is_none()) {
+            match provider.fetch_userinfo(&tokens.access_token).await {
+                // OIDC Core §5.3.2: the response must be about the same subject
+                Ok(info) if info.get("sub").and_then(|v| v.as_str()) == Some(access_claims.sub.as_str()) => {
+                    if let Some(info) = info.as_object() {
+                        access_claims = access_claims.merge_profile(info);
+                    }
                 }
-                // Note: keep roles from access token; ID token typically doesn't carry them
+                Ok(_) => tracing::warn!(provider = %registered.name, "userinfo subject does not match the token, ignoring it"),
+                Err(e) => tracing::debug!(provider = %registered.name, error = %e, "userinfo not available"),
             }
-        } else {
-            // (Optional fallback) If you didn’t enable the toggle, you could still call userinfo here.
-            // let ui = self.provider.fetch_userinfo(&tokens.access_token).await?;
-            // ...enrich access_claims from ui...
         }
 
         // Persist user
-        self.user_service.sync_user_from_claims(&access_claims).await?;
+        let user = self.user_service.sync_user_from_claims(&access_claims).await?;
+
+        if let Some(rt) = &tokens.refresh_token {
+            self.refresh_tokens.track_issued(&registered.name, rt, &access_claims).await
+                .map_err(|e| OidcError::Internal(format!("refresh token tracking failed: {e}")))?;
+        }
+
+        Ok(AuthenticatedTokens { tokens, claims: access_claims, user })
+    }
+
+
+    /// Refresh with rotation tracking. Presenting an already rotated token revokes its
+    /// whole family, at the provider too, and fails with `RefreshTokenReuse`. Tokens of a
+    /// revoked family (logged out) or of no known login fail with `RefreshTokenRevoked`.
+    ///
+    /// Concurrent refreshes of one token (parallel SPA requests after the access token
+    /// expired) share a single provider call, and requests arriving shortly after get
+    /// the same result instead of tripping reuse detection.
+    pub async fn refresh_access_token(&self, provider: Option<&str>, refresh_token: &str) -> Result<TokenResponse, OidcError> {
+        let registered = self.providers.get(provider)?;
+        let key = token_cache_key(&format!("{}\n{}", registered.name, refresh_token));
+
+        self.refreshes
+            .try_get_with(key, self.rotate_refresh_token(registered, refresh_token))
+            .await
+            .map_err(|e| Arc::try_unwrap(e).unwrap_or_else(|shared| shared.duplicate()))
+    }
+
+    async fn rotate_refresh_token(&self, registered: &RegisteredProvider, refresh_token: &str) -> Result<TokenResponse, OidcError> {
+        let tracking_failed = |e| OidcError::Internal(format!("refresh token tracking failed: {e}"));
+
+        let (family_id, tokens) = match self.refresh_tokens.check(&registered.name, refresh_token).await.map_err(tracking_failed)? {
+            RefreshTokenStatus::Active(id) => (id, registered.provider.refresh_access_token(refresh_token).await?),
+            RefreshTokenStatus::Unbound => {
+                if !self.refresh_tokens.adoption_open().await.map_err(tracking_failed)? {
+                    tracing::warn!(provider = %registered.name, "refresh token not bound to a known login, refusing it");
+                    return Err(OidcError::RefreshTokenRevoked("login of this refresh token is unknown".into()));
+                }
+
+                // Issued before login tracking: bind it to the login its refresh belongs to
+                let tokens = registered.provider.refresh_access_token(refresh_token).await?;
+                let login = match &tokens.id_token {
+                    Some(id_token) => registered.provider.validate_id_token(id_token).await?,
+                    None => registered.provider.validate_access_token(&tokens.access_token).await?,
+                };
+                let family_id = self.refresh_tokens.adopt(&registered.name, refresh_token, &login).await
+                    .map_err(tracking_failed)?;
+                tracing::info!(provider = %registered.name, %family_id, "adopted refresh token issued before login tracking");
+                (family_id, tokens)
+            }
+            RefreshTokenStatus::Revoked => return Err(OidcError::RefreshTokenRevoked("refresh token family revoked".into())),
+            RefreshTokenStatus::Reused { family_id, current } => {
+                for token in std::iter::once(refresh_token).chain(current.as_deref()) {
+                    if let Err(e) = registered.provider.revoke_token(token, Some(TokenTypeHint::RefreshToken)).await {
+                        tracing::warn!(provider = %registered.name, %family_id, error = %e, "failed to revoke refresh token family at provider");
+                    }
+                }
+                return Err(OidcError::RefreshTokenReuse);
+            }
+        };
+
+        if let Some(new_rt) = tokens.refresh_token.as_deref()
+            && new_rt != refresh_token
+        {
+            self.refresh_tokens.record_rotation(&registered.name, family_id, refresh_token, new_rt).await
+                .map_err(tracking_failed)?;
+        }
 
         Ok(tokens)
     }
 
+    /// Get a token for the downstream service `service` that carries the user's identity
+    /// (RFC 8693 token exchange). `subject_token` is the access token `claims` were validated
+    /// from (`AccessToken`); results are cached per subject token and audience until shortly
+    /// before either token expires, so a new login or a logged-out token never reuses them.
+    pub async fn exchange_for_service(
+        &self,
+        claims: &OidcClaims,
+        subject_token: &str,
+        service: &str,
+    ) -> Result<TokenResponse, OidcError> {
+        let audience = self.downstream_services.get(service)
+            .ok_or_else(|| OidcError::UnknownService(service.to_string()))?;
+        let registered = self.providers.by_issuer(&claims.iss)
+            .ok_or_else(|| OidcError::InvalidClaim("iss", format!("untrusted issuer {}", claims.iss)))?;
 
-    pub async fn refresh_access_token(&self, refresh_token: &str) -> Result<TokenResponse, OidcError> {
-        self.provider.refresh_access_token(refresh_token).await
+        let key = format!("{}::{}", token_cache_key(subject_token), audience);
+        let exchange = async {
+            let tokens = registered.provider.exchange_token(subject_token, audience).await?;
+            Ok::<_, OidcError>(ExchangedToken { tokens, subject_exp: claims.exp })
+        };
+        self.exchanged
+            .try_get_with(key, exchange)
+            .await
+            .map(|exchanged| exchanged.tokens)
+            .map_err(|e| Arc::try_unwrap(e).unwrap_or_else(|shared| shared.duplicate()))
     }
 
+    /// Validate an access token with the provider matching its `iss` claim.
+    /// Opaque tokens carry no issuer, so they are introspected at each provider that opted in
+    /// (`OIDC_<NAME>_INTROSPECT_OPAQUE`, the default provider by default). A token every such
+    /// provider rejected is refused without another round of introspection for a short while.
     pub async fn validate(&self, token: &str) -> Result<OidcClaims, OidcError> {
-        self.provider.validate_access_token(token).await
+        if looks_like_jwt(token) {
+            return self.providers.for_token(token)?.provider.validate_access_token(token).await;
+        }
+
+        let key = token_cache_key(token);
+        if self.rejected_opaque.contains_key(&key) {
+            return Err(OidcError::Jwt("opaque token not accepted by any provider".into()));
+        }
+
+        let mut last_err = OidcError::Jwt("opaque token not accepted by any provider".into());
+        let mut transient = false;
+        for p in self.providers.all().iter().filter(|p| p.introspect_opaque) {
+            match p.provider.validate_access_token(token).await {
+                Ok(claims) => return Ok(claims),
+                Err(e) => {
+                    tracing::debug!(provider = %p.name, error = %e, "opaque token rejected");
+                    // An unreachable provider hasn't judged the token; don't remember it as rejected
+                    transient |= matches!(e, OidcError::Network(_) | OidcError::Unavailable(_) | OidcError::Provider(_) | OidcError::Internal(_));
+                    last_err = e;
+                }
+            }
+        }
+        if !transient {
+            self.rejected_opaque.insert(key, ()).await;
+        }
+        Err(last_err)
+    }
+
+    pub async fn build_authorize_url(&self, provider: Option<&str>, request: &AuthorizationRequest) -> Result<String, OidcError> {
+        self.providers.get(provider)?.provider.authorize_url(request).await
     }
 
-    pub async fn build_authorize_url(&self, code_challenge: Option<&str>, state: Option<String>) -> String {
-        self.provider.authorize_url(state, code_challenge).await
+    pub async fn revoke_token(&self, provider: Option<&str>, token: &str, hint: Option<TokenTypeHint>) -> Result<(), OidcError> {
+        self.providers.get(provider)?.provider.revoke_token(token, hint).await
     }
 
-    pub async fn revoke_token(&self, token: &str) -> Result<(), OidcError> {
-        self.provider.revoke_token(token).await
+    /// Where to send the browser to end the provider's SSO session, if it supports it.
+    pub fn end_session_url(&self, provider: Option<&str>, id_token_hint: Option<&str>) -> Result<Option<String>, OidcError> {
+        Ok(self.providers.get(provider)?.provider.end_session_url(id_token_hint))
     }
 }
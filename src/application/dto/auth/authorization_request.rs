@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use crate::shared::errors::ServiceError;
+
+const PROMPT_VALUES: &[&str] = &["none", "login", "consent", "select_account", "create"];
+
+/// Parameters of one authorization request (the browser redirect to the provider).
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct AuthorizationRequest {
+    pub state: Option<String>,
+    /// Echoed in the ID token's `nonce` claim, binding it to this login
+    pub nonce: Option<String>,
+    /// PKCE S256 challenge
+    pub code_challenge: Option<String>,
+    /// Space separated requested `acr` values, in order of preference (step-up)
+    pub acr_values: Option<String>,
+    /// Maximum seconds since the user last authenticated; older logins must re-authenticate
+    pub max_age: Option<u64>,
+    /// Space separated `prompt` values, e.g. `login` to force re-authentication
+    pub prompt: Option<String>,
+    /// Username or email to pre-fill on the login page
+    pub login_hint: Option<String>,
+    /// Space separated BCP 47 language tags for the login UI, in order of preference
+    pub ui_locales: Option<String>,
+    /// Federated identity provider to send the user straight to (ZITADEL: `urn:zitadel:iam:org:idp:id:`)
+    pub idp_hint: Option<String>,
+    /// Organization to log in to (ZITADEL: `urn:zitadel:iam:org:id:`)
+    pub organization: Option<String>,
+    /// Scopes requested on top of the provider's configured ones
+    pub extra_scopes: Vec<String>,
+}
+
+impl AuthorizationRequest {
+    /// Check the caller-supplied login options before they are forwarded to the provider.
+    pub fn validate(&self) -> Result<(), ServiceError> {
+        if let Some(prompt) = &self.prompt {
+            let values = prompt.split(' ').collect::<Vec<_>>();
+            if values.iter().any(|v| !PROMPT_VALUES.contains(v)) {
+                return Err(ServiceError::Validation(format!("prompt must be one of: {}", PROMPT_VALUES.join(", "))));
+            }
+            if values.len() > 1 && values.contains(&"none") {
+                return Err(ServiceError::Validation("prompt=none cannot be combined with other values".into()));
+            }
+        }
+        if let Some(hint) = &self.login_hint
+            && (hint.is_empty() || hint.chars().count() > 256 || hint.chars().any(char::is_control))
+        {
+            return Err(ServiceError::Validation("login_hint must be 1 to 256 printable characters".into()));
+        }
+        if let Some(locales) = &self.ui_locales
+            && !space_separated(locales, 8, |tag| {
+                tag.len() <= 35 && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+        {
+            return Err(ServiceError::Validation("ui_locales must be up to 8 space separated language tags".into()));
+        }
+        if let Some(acr) = &self.acr_values
+            && !space_separated(acr, 8, |value| value.len() <= 128 && value.chars().all(is_token_char))
+        {
+            return Err(ServiceError::Validation("acr_values must be up to 8 space separated values".into()));
+        }
+        for (name, value) in [("idp_hint", &self.idp_hint), ("organization", &self.organization)] {
+            if let Some(value) = value
+                && (value.is_empty() || value.len() > 64 || !value.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)))
+            {
+                return Err(ServiceError::Validation(format!("{name} must be 1 to 64 letters, digits, '-', '_' or '.'")));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn space_separated(value: &str, max: usize, valid: impl Fn(&str) -> bool) -> bool {
+    let parts = value.split(' ').collect::<Vec<_>>();
+    parts.len() <= max && parts.iter().all(|p| !p.is_empty() && valid(p))
+}
+
+/// Characters allowed in a URI-ish token value (`acr` values are often URNs or URLs).
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~:/#".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(configure: impl FnOnce(&mut AuthorizationRequest)) -> AuthorizationRequest {
+        let mut request = AuthorizationRequest::default();
+        configure(&mut request);
+        request
+    }
+
+    #[test]
+    fn accepts_no_options() {
+        assert!(AuthorizationRequest::default().validate().is_ok());
+    }
+
+    #[test]
+    fn prompt_values() {
+        assert!(request(|r| r.prompt = Some("login consent".into())).validate().is_ok());
+        assert!(request(|r| r.prompt = Some("none".into())).validate().is_ok());
+        assert!(request(|r| r.prompt = Some("none login".into())).validate().is_err());
+        assert!(request(|r| r.prompt = Some("login none".into())).validate().is_err());
+        assert!(request(|r| r.prompt = Some("sudo".into())).validate().is_err());
+        assert!(request(|r| r.prompt = Some("login  consent".into())).validate().is_err());
+    }
+
+    #[test]
+    fn login_hint_length_and_characters() {
+        assert!(request(|r| r.login_hint = Some("alice@example.com".into())).validate().is_ok());
+        assert!(request(|r| r.login_hint = Some("ü".repeat(256))).validate().is_ok());
+        assert!(request(|r| r.login_hint = Some("a".repeat(257))).validate().is_err());
+        assert!(request(|r| r.login_hint = Some(String::new())).validate().is_err());
+        assert!(request(|r| r.login_hint = Some("alice\r\nX-Injected: 1".into())).validate().is_err());
+        assert!(request(|r| r.login_hint = Some("alice\u{0}".into())).validate().is_err());
+    }
+
+    #[test]
+    fn ui_locales_are_language_tags() {
+        assert!(request(|r| r.ui_locales = Some("de-CH de en".into())).validate().is_ok());
+        assert!(request(|r| r.ui_locales = Some("de_CH".into())).validate().is_err());
+        assert!(request(|r| r.ui_locales = Some("de  en".into())).validate().is_err());
+        assert!(request(|r| r.ui_locales = Some("en&prompt=none".into())).validate().is_err());
+        assert!(request(|r| r.ui_locales = Some("a".repeat(36))).validate().is_err());
+        assert!(request(|r| r.ui_locales = Some(["en"; 9].join(" "))).validate().is_err());
+    }
+
+    #[test]
+    fn acr_values_are_tokens() {
+        assert!(request(|r| r.acr_values = Some("urn:mace:incommon:iap:silver mfa".into())).validate().is_ok());
+        assert!(request(|r| r.acr_values = Some("mfa\"x".into())).validate().is_err());
+    }
+
+    #[test]
+    fn idp_hint_and_organization_are_identifiers() {
+        assert!(request(|r| r.organization = Some("123456789".into())).validate().is_ok());
+        assert!(request(|r| r.idp_hint = Some("google.workspace".into())).validate().is_ok());
+        assert!(request(|r| r.organization = Some("a/b".into())).validate().is_err());
+        assert!(request(|r| r.idp_hint = Some(String::new())).validate().is_err());
+        assert!(request(|r| r.idp_hint = Some("x".repeat(65))).validate().is_err());
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use crate::application::dto::auth::token_response::TokenResponse;
+
+/// Device authorization response (RFC 8628 §3.2), passed through to the device.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+    /// Minimum seconds between polls; RFC 8628 defaults to 5
+    pub interval: Option<u64>,
+}
+
+/// Outcome of one poll of the token endpoint with a device code (RFC 8628 §3.5).
+#[derive(Debug)]
+pub enum DeviceTokenPoll<T = TokenResponse> {
+    /// The user has not finished approving yet
+    Pending,
+    /// Polling too fast; the interval must grow by 5 seconds
+    SlowDown,
+    /// The user declined the request
+    Denied,
+    /// The device code expired; a new device login has to be started
+    Expired,
+    /// The provider rejected the device code (unknown, already used or issued to another client)
+    InvalidGrant,
+    Complete(T),
+}
@@ -1 +1,3 @@
-pub mod token_response;
\ No newline at end of file
+pub mod authorization_request;
+pub mod device_authorization;
+pub mod token_response;
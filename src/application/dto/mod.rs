@@ -1,3 +1,4 @@
 pub mod user_dto;
+pub(crate) mod api_key;
 pub(crate) mod auth;
 pub(crate) mod user;
\ No newline at end of file
@@ -9,6 +9,13 @@ pub struct UserResponse {
     pub id: Uuid,
     pub username: String,
     pub email: String,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+    pub locale: Option<String>,
+    pub org_id: Option<String>,
     pub roles: Vec<String>,
 }
 
@@ -18,6 +25,13 @@ impl From<(Arc<User>, &OidcClaims)> for UserResponse {
             id: user.id,
             username: user.username.clone(),
             email: user.email.clone(),
+            email_verified: user.email_verified,
+            name: user.name.clone(),
+            given_name: user.given_name.clone(),
+            family_name: user.family_name.clone(),
+            picture: user.picture.clone(),
+            locale: user.locale.clone(),
+            org_id: user.org_id.clone(),
             roles: claims.roles.clone(),
         }
     }
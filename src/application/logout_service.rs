@@ -0,0 +1,153 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use moka::future::Cache;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+use crate::application::refresh_token_service::RefreshTokenService;
+use crate::application::session_service::SessionService;
+use crate::application::user_service::UserService;
+use crate::domain::entities::LogoutMarker;
+use crate::domain::repositories::LogoutMarkerRepository;
+use crate::infrastructure::oidc::logout::LogoutToken;
+use crate::shared::errors::ServiceError;
+
+/// Applies back-channel logouts and answers whether a token belongs to a logged-out login.
+///
+/// Markers are kept for `retention` (the refresh token lifetime), which is long
+/// enough for every token issued before the logout to have expired.
+pub struct LogoutService {
+    repository: Arc<dyn LogoutMarkerRepository>,
+    user_service: Arc<UserService>,
+    session_service: Option<Arc<SessionService>>,
+    refresh_tokens: Arc<RefreshTokenService>,
+    retention: Duration,
+    /// `sub:<iss>::<sub>` -> unix time of the latest logout, `sid:<iss>::<sid>` -> 0 if logged out
+    cache: Cache<String, Option<u64>>,
+}
+
+impl LogoutService {
+    pub fn new(
+        repository: Arc<dyn LogoutMarkerRepository>,
+        user_service: Arc<UserService>,
+        session_service: Option<Arc<SessionService>>,
+        refresh_tokens: Arc<RefreshTokenService>,
+        retention: Duration,
+    ) -> Self {
+        let cache = Cache::builder()
+            .time_to_live(StdDuration::from_secs(30))
+            .max_capacity(10_000)
+            .build();
+
+        Self { repository, user_service, session_service, refresh_tokens, retention, cache }
+    }
+
+    /// Record a validated logout token from `provider`, end the matching sessions and revoke
+    /// the refresh tokens issued to that login. A logout with `sid` ends only that provider
+    /// session; one with just `sub` ends every login of the subject.
+    ///
+    /// Each token is applied once: a replayed `jti` is a `Validation` error.
+    pub async fn backchannel_logout(&self, provider: &str, token: &LogoutToken) -> Result<(), ServiceError> {
+        let now = OffsetDateTime::now_utc();
+        let replay_until = token.exp
+            .and_then(|exp| OffsetDateTime::from_unix_timestamp(exp as i64).ok())
+            .unwrap_or(now + self.retention);
+        if !self.repository.record_token_id(&token.iss, &token.jti, replay_until).await? {
+            return Err(ServiceError::Validation("logout token already processed".into()));
+        }
+
+        let result = self.apply_logout(provider, token, now).await;
+        if result.is_err()
+            && let Err(e) = self.repository.forget_token_id(&token.iss, &token.jti).await
+        {
+            tracing::error!(error = ?e, "Failed to release logout token id after a failed logout");
+        }
+        result
+    }
+
+    async fn apply_logout(&self, provider: &str, token: &LogoutToken, now: OffsetDateTime) -> Result<(), ServiceError> {
+        // With a `sid` only that provider session is logged out, not the subject's other logins
+        let subject = if token.sid.is_none() { token.sub.as_deref() } else { None };
+
+        self.repository.create(&LogoutMarker {
+            id: Uuid::new_v4(),
+            issuer: token.iss.clone(),
+            subject: subject.map(str::to_string),
+            sid: token.sid.clone(),
+            logged_out_at: now,
+            expires_at: now + self.retention,
+        }).await?;
+
+        if let Some(sub) = &token.sub {
+            self.user_service.evict_user(&token.iss, sub).await;
+        }
+        if let Some(sub) = subject {
+            self.cache.invalidate(&subject_key(&token.iss, sub)).await;
+        }
+        if let Some(sid) = &token.sid {
+            self.cache.invalidate(&sid_key(&token.iss, sid)).await;
+        }
+
+        let families = self.refresh_tokens
+            .revoke_for_login(&token.iss, subject, token.sid.as_deref())
+            .await?;
+        tracing::info!(provider, families, "Back-channel logout revoked refresh tokens");
+
+        if let Some(sessions) = &self.session_service {
+            let mut revoked = 0;
+            if let Some(sid) = &token.sid {
+                revoked += sessions.revoke_by_idp_sid(provider, sid).await?;
+            } else if let Some(sub) = subject
+                && let Some(user) = self.user_service.user_repository.find_by_subject(&token.iss, sub).await?
+            {
+                revoked += sessions.revoke_for_user(user.id).await?;
+            }
+            tracing::info!(provider, revoked, "Back-channel logout revoked sessions");
+        }
+
+        let purged = self.repository.delete_expired().await?;
+        if purged > 0 {
+            tracing::debug!(purged, "Purged expired logout markers and token ids");
+        }
+
+        Ok(())
+    }
+
+    /// Whether a token issued at `iat` for `sub` (in provider session `sid`) was logged out since.
+    pub async fn is_logged_out(&self, iss: &str, sub: &str, sid: Option<&str>, iat: u64) -> Result<bool, ServiceError> {
+        if let Some(sid) = sid {
+            let key = sid_key(iss, sid);
+            let marker = match self.cache.get(&key).await {
+                Some(marker) => marker,
+                None => {
+                    let marker = self.repository.exists_for_sid(iss, sid).await?.then_some(0);
+                    self.cache.insert(key, marker).await;
+                    marker
+                }
+            };
+            if marker.is_some() {
+                return Ok(true);
+            }
+        }
+
+        let key = subject_key(iss, sub);
+        let logged_out_at = match self.cache.get(&key).await {
+            Some(ts) => ts,
+            None => {
+                let ts = self.repository.latest_for_subject(iss, sub).await?
+                    .map(|t| t.unix_timestamp().max(0) as u64);
+                self.cache.insert(key, ts).await;
+                ts
+            }
+        };
+
+        Ok(logged_out_at.is_some_and(|ts| iat <= ts))
+    }
+}
+
+fn subject_key(issuer: &str, subject: &str) -> String {
+    format!("sub:{}::{}", issuer, subject)
+}
+
+fn sid_key(issuer: &str, sid: &str) -> String {
+    format!("sid:{}::{}", issuer, sid)
+}
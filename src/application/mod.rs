@@ -1,4 +1,9 @@
 pub mod auth_service;
 pub mod user_service;
+pub mod agent_service;
+pub mod api_key_service;
+pub mod session_service;
+pub mod logout_service;
+pub mod refresh_token_service;
 pub mod dto;
-pub mod user_sync;
\ No newline at end of file
+pub mod user_sync;
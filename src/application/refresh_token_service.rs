@@ -0,0 +1,386 @@
+use std::sync::Arc;
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+use crate::domain::entities::{RefreshTokenFamily, SecurityEvent};
+use crate::domain::repositories::{RefreshTokenRepository, SecurityEventRepository};
+use crate::infrastructure::crypto::TokenCipher;
+use crate::infrastructure::oidc::OidcClaims;
+use crate::shared::errors::ServiceError;
+
+pub const REFRESH_TOKEN_REUSE_EVENT: &str = "refresh_token_reuse";
+
+/// Where a presented refresh token stands in its family.
+pub enum RefreshTokenStatus {
+    /// Never seen, or tracked before families recorded their login: a back-channel logout
+    /// couldn't reach it. It is adopted while the adoption window is open, refused after.
+    Unbound,
+    /// Latest token of a live family
+    Active(Uuid),
+    /// The token had already been rotated. The family is now revoked; `current` is its
+    /// latest token (if stored) so it can be revoked at the provider as well.
+    Reused { family_id: Uuid, current: Option<String> },
+    /// Belongs to a family revoked earlier
+    Revoked,
+}
+
+/// Tracks refresh-token rotation per login so a replayed (already rotated) token
+/// is detected. Tokens are stored as SHA-256 hashes; only a family's latest token
+/// is kept, encrypted, and only when an encryption key is configured.
+pub struct RefreshTokenService {
+    repository: Arc<dyn RefreshTokenRepository>,
+    events: Arc<dyn SecurityEventRepository>,
+    cipher: Option<TokenCipher>,
+    retention: Duration,
+}
+
+impl RefreshTokenService {
+    pub fn new(
+        repository: Arc<dyn RefreshTokenRepository>,
+        events: Arc<dyn SecurityEventRepository>,
+        cipher: Option<TokenCipher>,
+        retention: Duration,
+    ) -> Self {
+        Self { repository, events, cipher, retention }
+    }
+
+    fn aad(family_id: Uuid) -> Vec<u8> {
+        let mut aad = family_id.as_bytes().to_vec();
+        aad.extend_from_slice(b"refresh");
+        aad
+    }
+
+    fn seal(&self, family_id: Uuid, token: &str) -> Result<Option<Vec<u8>>, ServiceError> {
+        self.cipher.as_ref()
+            .map(|c| c.encrypt(token, &Self::aad(family_id)))
+            .transpose()
+    }
+
+    /// Start a family for a refresh token issued at the login described by `login`.
+    pub async fn track_issued(&self, provider: &str, token: &str, login: &OidcClaims) -> Result<Uuid, ServiceError> {
+        let id = Uuid::new_v4();
+        let now = OffsetDateTime::now_utc();
+
+        let family = RefreshTokenFamily {
+            id,
+            provider: provider.to_string(),
+            current_token: self.seal(id, token)?,
+            revoked_at: None,
+            created_at: now,
+            updated_at: now,
+            issuer: Some(login.iss.clone()),
+            subject: Some(login.sub.clone()),
+            sid: login.sid.clone(),
+        };
+
+        self.repository.create_family(&family, &hash_token(token)).await?;
+        Ok(id)
+    }
+
+    /// Adopt a refresh token issued before login tracking (`Unbound`) into a family bound to
+    /// `login`, the login its refresh was issued to: a new family for a token never seen, or
+    /// the token's existing family if that lacks its login.
+    pub async fn adopt(&self, provider: &str, token: &str, login: &OidcClaims) -> Result<Uuid, ServiceError> {
+        let family = match self.repository.find_token(&hash_token(token)).await? {
+            Some(record) => self.repository.find_family(record.family_id).await?,
+            None => None,
+        };
+        let Some(family) = family else {
+            return self.track_issued(provider, token, login).await;
+        };
+
+        self.repository.bind_login(family.id, &login.iss, &login.sub, login.sid.as_deref()).await?;
+        Ok(family.id)
+    }
+
+    /// Whether refresh tokens issued before login tracking are still adopted: for one refresh
+    /// token lifetime after the tracking was deployed, when every such token has expired.
+    pub async fn adoption_open(&self) -> Result<bool, ServiceError> {
+        let started_at = self.repository.adoption_started_at().await?;
+        Ok(started_at.is_some_and(|t| OffsetDateTime::now_utc() < t + self.retention))
+    }
+
+    /// Look up a presented token. Reuse of a rotated token revokes its family
+    /// and records a security event before returning. A token tracked for another
+    /// provider is treated as revoked.
+    pub async fn check(&self, provider: &str, token: &str) -> Result<RefreshTokenStatus, ServiceError> {
+        let Some(record) = self.repository.find_token(&hash_token(token)).await? else {
+            return Ok(RefreshTokenStatus::Unbound);
+        };
+        let Some(family) = self.repository.find_family(record.family_id).await? else {
+            return Ok(RefreshTokenStatus::Unbound);
+        };
+
+        if family.provider != provider {
+            tracing::warn!(provider, family_provider = %family.provider, family_id = %family.id, "refresh token presented to another provider");
+            return Ok(RefreshTokenStatus::Revoked);
+        }
+        if family.revoked_at.is_some() {
+            return Ok(RefreshTokenStatus::Revoked);
+        }
+        if record.rotated_at.is_none() {
+            if family.issuer.is_none() || family.subject.is_none() {
+                return Ok(RefreshTokenStatus::Unbound);
+            }
+            return Ok(RefreshTokenStatus::Active(family.id));
+        }
+
+        let current = match (&self.cipher, &family.current_token) {
+            (Some(cipher), Some(blob)) => match cipher.decrypt(blob, &Self::aad(family.id)) {
+                Ok(token) => Some(token),
+                Err(e) => {
+                    tracing::warn!(family_id = %family.id, error = %e, "could not decrypt current refresh token");
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        self.repository.revoke_family(family.id).await?;
+        self.events.record(&SecurityEvent {
+            id: Uuid::new_v4(),
+            kind: REFRESH_TOKEN_REUSE_EVENT.to_string(),
+            provider: Some(provider.to_string()),
+            subject: None,
+            details: serde_json::json!({
+                "family_id": family.id,
+                "family_created_at": family.created_at.unix_timestamp(),
+                "rotated_at": record.rotated_at.map(|t| t.unix_timestamp()),
+            }),
+            created_at: OffsetDateTime::now_utc(),
+        }).await?;
+
+        tracing::warn!(provider, family_id = %family.id, "Refresh token reuse detected - family revoked");
+        Ok(RefreshTokenStatus::Reused { family_id: family.id, current })
+    }
+
+    /// Record that `old` was exchanged for `new`.
+    pub async fn record_rotation(
+        &self,
+        provider: &str,
+        family_id: Uuid,
+        old: &str,
+        new: &str,
+    ) -> Result<(), ServiceError> {
+        let sealed = self.seal(family_id, new)?;
+        if !self.repository.rotate(family_id, &hash_token(old), &hash_token(new), sealed.as_deref()).await? {
+            tracing::warn!(provider, family_id = %family_id, "refresh token was rotated concurrently");
+        }
+        Ok(())
+    }
+
+    /// Revoke the refresh tokens of a login ended by a back-channel logout: those of
+    /// provider session `sid` if given, otherwise all of `subject`.
+    pub async fn revoke_for_login(&self, issuer: &str, subject: Option<&str>, sid: Option<&str>) -> Result<u64, ServiceError> {
+        Ok(self.repository.revoke_for_login(issuer, subject, sid).await?)
+    }
+
+    /// Drop families unused for longer than the refresh token lifetime.
+    pub async fn purge_stale(&self) -> Result<u64, ServiceError> {
+        Ok(self.repository.delete_stale(OffsetDateTime::now_utc() - self.retention).await?)
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Periodically remove stale refresh token families.
+pub async fn refresh_token_cleanup_loop(refresh_tokens: Arc<RefreshTokenService>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+
+    loop {
+        interval.tick().await;
+
+        match refresh_tokens.purge_stale().await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Purged {} stale refresh token families", n),
+            Err(e) => tracing::error!("Refresh token cleanup failed: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use crate::domain::entities::RefreshTokenRecord;
+
+    /// Families and tokens in memory; only what `RefreshTokenService` reads and writes.
+    #[derive(Default)]
+    struct MemoryRepo {
+        families: Mutex<HashMap<Uuid, RefreshTokenFamily>>,
+        tokens: Mutex<HashMap<String, RefreshTokenRecord>>,
+        adoption_started_at: Option<OffsetDateTime>,
+    }
+
+    impl MemoryRepo {
+        fn add_token(&self, token: &str, family_id: Uuid, rotated: bool) {
+            let now = OffsetDateTime::now_utc();
+            self.tokens.lock().unwrap().insert(hash_token(token), RefreshTokenRecord {
+                token_hash: hash_token(token),
+                family_id,
+                rotated_at: rotated.then_some(now),
+                created_at: now,
+            });
+        }
+    }
+
+    #[async_trait]
+    impl RefreshTokenRepository for MemoryRepo {
+        async fn create_family(&self, family: &RefreshTokenFamily, token_hash: &str) -> Result<(), sqlx::Error> {
+            self.families.lock().unwrap().insert(family.id, family.clone());
+            self.tokens.lock().unwrap().entry(token_hash.to_string()).or_insert(RefreshTokenRecord {
+                token_hash: token_hash.to_string(),
+                family_id: family.id,
+                rotated_at: None,
+                created_at: family.created_at,
+            });
+            Ok(())
+        }
+
+        async fn find_family(&self, id: Uuid) -> Result<Option<RefreshTokenFamily>, sqlx::Error> {
+            Ok(self.families.lock().unwrap().get(&id).cloned())
+        }
+
+        async fn find_token(&self, token_hash: &str) -> Result<Option<RefreshTokenRecord>, sqlx::Error> {
+            Ok(self.tokens.lock().unwrap().get(token_hash).cloned())
+        }
+
+        async fn rotate(&self, _: Uuid, _: &str, _: &str, _: Option<&[u8]>) -> Result<bool, sqlx::Error> {
+            unimplemented!()
+        }
+
+        async fn revoke_family(&self, id: Uuid) -> Result<(), sqlx::Error> {
+            if let Some(family) = self.families.lock().unwrap().get_mut(&id) {
+                family.revoked_at = Some(OffsetDateTime::now_utc());
+            }
+            Ok(())
+        }
+
+        async fn bind_login(&self, id: Uuid, issuer: &str, subject: &str, sid: Option<&str>) -> Result<(), sqlx::Error> {
+            if let Some(family) = self.families.lock().unwrap().get_mut(&id) {
+                family.issuer = Some(issuer.to_string());
+                family.subject = Some(subject.to_string());
+                family.sid = sid.map(str::to_string);
+            }
+            Ok(())
+        }
+
+        async fn adoption_started_at(&self) -> Result<Option<OffsetDateTime>, sqlx::Error> {
+            Ok(self.adoption_started_at)
+        }
+
+        async fn revoke_for_login(&self, _: &str, _: Option<&str>, _: Option<&str>) -> Result<u64, sqlx::Error> {
+            unimplemented!()
+        }
+
+        async fn delete_stale(&self, _: OffsetDateTime) -> Result<u64, sqlx::Error> {
+            unimplemented!()
+        }
+    }
+
+    struct NoEvents;
+
+    #[async_trait]
+    impl SecurityEventRepository for NoEvents {
+        async fn record(&self, _: &SecurityEvent) -> Result<(), sqlx::Error> {
+            Ok(())
+        }
+    }
+
+    fn service(repo: &Arc<MemoryRepo>) -> RefreshTokenService {
+        RefreshTokenService::new(repo.clone(), Arc::new(NoEvents), None, Duration::days(30))
+    }
+
+    fn login() -> OidcClaims {
+        OidcClaims::from_value(&json!({
+            "iss": "https://idp.example.com",
+            "sub": "user-1",
+            "sid": "session-1",
+            "exp": 4_000_000_000u64,
+        }))
+        .unwrap()
+    }
+
+    /// A family tracked before families recorded their login.
+    fn legacy_family(repo: &MemoryRepo, provider: &str) -> Uuid {
+        let now = OffsetDateTime::now_utc();
+        let id = Uuid::new_v4();
+        repo.families.lock().unwrap().insert(id, RefreshTokenFamily {
+            id,
+            provider: provider.to_string(),
+            current_token: None,
+            revoked_at: None,
+            created_at: now,
+            updated_at: now,
+            issuer: None,
+            subject: None,
+            sid: None,
+        });
+        id
+    }
+
+    #[tokio::test]
+    async fn token_of_another_provider_is_revoked() {
+        let repo = Arc::new(MemoryRepo::default());
+        let service = service(&repo);
+        service.track_issued("zitadel", "rt-1", &login()).await.unwrap();
+
+        assert!(matches!(service.check("zitadel", "rt-1").await.unwrap(), RefreshTokenStatus::Active(_)));
+        assert!(matches!(service.check("keycloak", "rt-1").await.unwrap(), RefreshTokenStatus::Revoked));
+    }
+
+    #[tokio::test]
+    async fn unseen_token_is_adopted_into_a_new_family() {
+        let repo = Arc::new(MemoryRepo::default());
+        let service = service(&repo);
+        assert!(matches!(service.check("zitadel", "rt-1").await.unwrap(), RefreshTokenStatus::Unbound));
+
+        let family_id = service.adopt("zitadel", "rt-1", &login()).await.unwrap();
+
+        assert!(matches!(service.check("zitadel", "rt-1").await.unwrap(), RefreshTokenStatus::Active(id) if id == family_id));
+        let family = repo.families.lock().unwrap()[&family_id].clone();
+        assert_eq!(family.subject.as_deref(), Some("user-1"));
+        assert_eq!(family.sid.as_deref(), Some("session-1"));
+    }
+
+    #[tokio::test]
+    async fn legacy_family_is_bound_on_adoption() {
+        let repo = Arc::new(MemoryRepo::default());
+        let service = service(&repo);
+        let family_id = legacy_family(&repo, "zitadel");
+        repo.add_token("rt-1", family_id, false);
+        assert!(matches!(service.check("zitadel", "rt-1").await.unwrap(), RefreshTokenStatus::Unbound));
+
+        assert_eq!(service.adopt("zitadel", "rt-1", &login()).await.unwrap(), family_id);
+
+        assert!(matches!(service.check("zitadel", "rt-1").await.unwrap(), RefreshTokenStatus::Active(id) if id == family_id));
+        assert_eq!(repo.families.lock().unwrap()[&family_id].issuer.as_deref(), Some("https://idp.example.com"));
+    }
+
+    #[tokio::test]
+    async fn rotated_token_of_legacy_family_is_reuse() {
+        let repo = Arc::new(MemoryRepo::default());
+        let service = service(&repo);
+        let family_id = legacy_family(&repo, "zitadel");
+        repo.add_token("rt-1", family_id, true);
+
+        assert!(matches!(service.check("zitadel", "rt-1").await.unwrap(), RefreshTokenStatus::Reused { .. }));
+        assert!(repo.families.lock().unwrap()[&family_id].revoked_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn adoption_closes_after_one_refresh_token_lifetime() {
+        let now = OffsetDateTime::now_utc();
+        let open = Arc::new(MemoryRepo { adoption_started_at: Some(now - Duration::days(29)), ..Default::default() });
+        let closed = Arc::new(MemoryRepo { adoption_started_at: Some(now - Duration::days(31)), ..Default::default() });
+
+        assert!(service(&open).adoption_open().await.unwrap());
+        assert!(!service(&closed).adoption_open().await.unwrap());
+        assert!(!service(&Arc::new(MemoryRepo::default())).adoption_open().await.unwrap());
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+use crate::application::dto::auth::token_response::TokenResponse;
+use crate::domain::entities::Session;
+use crate::domain::repositories::SessionRepository;
+use crate::infrastructure::crypto::{TokenCipher, ValueSigner};
+use crate::shared::errors::ServiceError;
+
+/// Decrypted view of a live session.
+pub struct ActiveSession {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// Kept as `id_token_hint` for RP-initiated logout
+    pub id_token: Option<String>,
+}
+
+/// Server-side sessions (backend-for-frontend mode): the browser only holds a
+/// signed session id, tokens stay in Postgres encrypted at rest.
+pub struct SessionService {
+    repository: Arc<dyn SessionRepository>,
+    cipher: TokenCipher,
+    signer: ValueSigner,
+    ttl: Duration,
+}
+
+impl SessionService {
+    pub fn new(
+        repository: Arc<dyn SessionRepository>,
+        cipher: TokenCipher,
+        signer: ValueSigner,
+        ttl: Duration,
+    ) -> Self {
+        Self { repository, cipher, signer, ttl }
+    }
+
+    /// Associated data ties each ciphertext to its session and column.
+    fn aad(id: Uuid, field: &str) -> Vec<u8> {
+        let mut aad = id.as_bytes().to_vec();
+        aad.extend_from_slice(field.as_bytes());
+        aad
+    }
+
+    /// Store a new session and return the signed value for the session cookie.
+    /// `idp_sid` is the provider's session id, used to match back-channel logouts.
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        idp_sid: Option<&str>,
+        tokens: &TokenResponse,
+    ) -> Result<String, ServiceError> {
+        let id = Uuid::new_v4();
+        let now = OffsetDateTime::now_utc();
+
+        let session = Session {
+            id,
+            user_id,
+            provider: provider.to_string(),
+            idp_sid: idp_sid.map(str::to_string),
+            access_token: self.cipher.encrypt(&tokens.access_token, &Self::aad(id, "access"))?,
+            refresh_token: tokens.refresh_token.as_deref()
+                .map(|rt| self.cipher.encrypt(rt, &Self::aad(id, "refresh")))
+                .transpose()?,
+            id_token: tokens.id_token.as_deref()
+                .map(|idt| self.cipher.encrypt(idt, &Self::aad(id, "id")))
+                .transpose()?,
+            expires_at: now + self.ttl,
+            revoked_at: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        self.repository.create(&session).await?;
+        tracing::debug!(session_id = %id, "session created");
+        Ok(self.signer.sign(&id.to_string()))
+    }
+
+    /// Verify the cookie signature and load the session, if it is still active.
+    pub async fn resolve(&self, cookie_value: &str) -> Result<Option<ActiveSession>, ServiceError> {
+        let Some(id) = self.signer.verify(cookie_value).and_then(|v| Uuid::parse_str(v).ok()) else {
+            tracing::warn!("session cookie with invalid signature");
+            return Ok(None);
+        };
+
+        let Some(session) = self.repository.find_active(id).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(ActiveSession {
+            id,
+            user_id: session.user_id,
+            provider: session.provider,
+            access_token: self.cipher.decrypt(&session.access_token, &Self::aad(id, "access"))?,
+            refresh_token: session.refresh_token
+                .map(|rt| self.cipher.decrypt(&rt, &Self::aad(id, "refresh")))
+                .transpose()?,
+            id_token: session.id_token
+                .map(|idt| self.cipher.decrypt(&idt, &Self::aad(id, "id")))
+                .transpose()?,
+        }))
+    }
+
+    /// Replace the stored tokens after a refresh. A missing refresh or ID token keeps the old one.
+    pub async fn update_tokens(&self, id: Uuid, tokens: &TokenResponse) -> Result<(), ServiceError> {
+        let access = self.cipher.encrypt(&tokens.access_token, &Self::aad(id, "access"))?;
+        let refresh = tokens.refresh_token.as_deref()
+            .map(|rt| self.cipher.encrypt(rt, &Self::aad(id, "refresh")))
+            .transpose()?;
+        let id_token = tokens.id_token.as_deref()
+            .map(|idt| self.cipher.encrypt(idt, &Self::aad(id, "id")))
+            .transpose()?;
+
+        self.repository.update_tokens(id, &access, refresh.as_deref(), id_token.as_deref()).await?;
+        Ok(())
+    }
+
+    pub async fn revoke(&self, id: Uuid) -> Result<(), ServiceError> {
+        self.repository.revoke(id).await?;
+        Ok(())
+    }
+
+    /// Revoke every session of a user (back-channel logout by `sub`).
+    pub async fn revoke_for_user(&self, user_id: Uuid) -> Result<u64, ServiceError> {
+        Ok(self.repository.revoke_for_user(user_id).await?)
+    }
+
+    /// Revoke the sessions belonging to one provider session (back-channel logout by `sid`).
+    pub async fn revoke_by_idp_sid(&self, provider: &str, sid: &str) -> Result<u64, ServiceError> {
+        Ok(self.repository.revoke_by_idp_sid(provider, sid).await?)
+    }
+
+    pub async fn purge_expired(&self) -> Result<u64, ServiceError> {
+        Ok(self.repository.delete_expired().await?)
+    }
+}
+
+/// Periodically delete expired and revoked sessions.
+pub async fn session_cleanup_loop(sessions: Arc<SessionService>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+
+    loop {
+        interval.tick().await;
+
+        match sessions.purge_expired().await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Purged {} expired sessions", n),
+            Err(e) => tracing::error!("Session cleanup failed: {:?}", e),
+        }
+    }
+}
@@ -4,35 +4,44 @@ use std::sync::Arc;
 use sqlx::Error;
 use tokio::sync::Mutex;
 use moka::future::Cache;
-use crate::domain::entities::User;
+use crate::domain::entities::{User, UserProfile};
+use crate::application::agent_service::AgentService;
 use crate::domain::repositories::UserRepository;
-use crate::infrastructure::oidc::{OidcClaims, OidcError};
+use crate::infrastructure::oidc::{OidcClaims, OidcError, PrincipalKind};
 use crate::infrastructure::oidc::provider::OidcAdminApi;
 
 fn id_key(issuer: &str, subject: &str) -> String {
     format!("{}::{}", issuer, subject)
 }
 
+/// Admin API client for one provider, together with the issuer its users belong to.
+#[derive(Clone)]
+pub struct ManagedIssuer {
+    pub issuer: String,
+    pub client: Arc<Mutex<dyn OidcAdminApi + Send + Sync>>,
+}
+
 #[derive(Clone)]
 pub struct UserService {
     pub user_repository: Arc<dyn UserRepository>,
     pub cache: Cache<String, Arc<User>>,
-    pub management_client: Option<Arc<Mutex<dyn OidcAdminApi + Send + Sync>>>,
-    pub issuer_url: String,
+    /// Machine users from the admin API are synced as agents
+    pub agent_service: Arc<AgentService>,
+    pub management_clients: Vec<ManagedIssuer>,
 }
 
 impl UserService {
     pub fn new(
         user_repository: Arc<dyn UserRepository>,
         cache: Cache<String, Arc<User>>,
-        management_client: Option<Arc<Mutex<dyn OidcAdminApi + Send + Sync>>>,
-        issuer_url: String,
+        agent_service: Arc<AgentService>,
+        management_clients: Vec<ManagedIssuer>,
     ) -> Self {
         Self {
             user_repository,
             cache,
-            management_client,
-            issuer_url,
+            agent_service,
+            management_clients,
         }
     }
 
@@ -82,7 +91,11 @@ impl UserService {
     pub async fn sync_user_from_claims(
         &self,
         claims: &OidcClaims,
-    ) -> Result<(), OidcError> {
+    ) -> Result<Arc<User>, OidcError> {
+        if claims.is_machine() {
+            return Err(OidcError::Provider("Machine principals are agents, not users".to_string()));
+        }
+
         let issuer = &claims.iss;
         let sub = &claims.sub;
 
@@ -95,22 +108,26 @@ impl UserService {
         let email = claims.email.clone()
             .ok_or_else(|| OidcError::Provider("Email is required".to_string()))?;
 
-        self.upsert_and_cache_user(issuer, sub, &username, &email)
+        self.upsert_and_cache_user(issuer, sub, &username, &email, &claims.profile())
             .await
-            .map_err(|e| OidcError::Provider(format!("User upsert failed: {}", e)))?;
-
-        Ok(())
+            .map_err(|e| OidcError::Provider(format!("User upsert failed: {}", e)))
     }
 
     /// Full sync from ZITADEL Management API
     pub async fn sync_users(&self) -> anyhow::Result<()> {
         tracing::info!("Starting user sync");
 
-        if let Some(client) = &self.management_client {
-            tracing::info!("Fetching users from ZITADEL Management API");
+        if self.management_clients.is_empty() {
+            // Just refresh cache from DB if no management client
+            tracing::info!("No ZITADEL Management client - refreshing cache from database");
+            self.refresh_cache().await?;
+        }
+
+        for managed in &self.management_clients {
+            tracing::info!(issuer = %managed.issuer, "Fetching users from ZITADEL Management API");
 
             // Get all users from ZITADEL
-            let client = client.lock().await;
+            let client = managed.client.lock().await;
             let users = client.fetch_all_users().await?;
 
             let mut synced_count = 0;
@@ -118,6 +135,21 @@ impl UserService {
 
             // Sync each user to database
             for user in users {
+                if user.kind == PrincipalKind::Machine {
+                    let name = user.username.as_deref().unwrap_or(&user.idp_subject);
+                    match self.agent_service
+                        .upsert_and_cache_agent(&managed.issuer, &user.idp_subject, name, None, false)
+                        .await
+                    {
+                        Ok(_) => synced_count += 1,
+                        Err(e) => {
+                            tracing::error!("Failed to sync agent {}: {}", name, e);
+                            error_count += 1;
+                        }
+                    }
+                    continue;
+                }
+
                 let username = user.username.unwrap_or_else(|| user.idp_subject.clone());
                 let email = match user.email {
                     Some(email) => email,
@@ -127,7 +159,7 @@ impl UserService {
                     }
                 };
 
-                match self.upsert_and_cache_user(&self.issuer_url, &user.idp_subject, &username, &email)
+                match self.upsert_and_cache_user(&managed.issuer, &user.idp_subject, &username, &email, &UserProfile::default())
                     .await
                 {
                     Ok(_) => synced_count += 1,
@@ -139,8 +171,9 @@ impl UserService {
             }
 
             tracing::info!(
-                "ZITADEL sync completed: {} users synced, {} errors", 
-                synced_count, 
+                "ZITADEL sync completed for {}: {} users synced, {} errors",
+                managed.issuer,
+                synced_count,
                 error_count
             );
 
@@ -152,16 +185,12 @@ impl UserService {
 
             let db_identities = self.get_all_identities().await?;
             for (db_issuer, db_subject) in db_identities {
-                if db_issuer == self.issuer_url && !zitadel_subjects.contains(&db_subject) {
+                if db_issuer == managed.issuer && !zitadel_subjects.contains(&db_subject) {
                     tracing::warn!("Removing user {} - no longer in ZITADEL", db_subject);
                     self.remove_user_from_cache_and_db(&db_issuer, &db_subject).await?;
                 }
             }
             */
-        } else {
-            // Just refresh cache from DB if no management client
-            tracing::info!("No ZITADEL Management client - refreshing cache from database");
-            self.refresh_cache().await?;
         }
 
         tracing::info!(
@@ -177,15 +206,20 @@ impl UserService {
         issuer: &str,
         subject: &str,
         username: &str,
-        email: &str
+        email: &str,
+        profile: &UserProfile,
     ) -> Result<Arc<User>, sqlx::Error> {
-        let user = self.user_repository.upsert_user(issuer, subject, username, email).await?;
+        let user = self.user_repository.upsert_user(issuer, subject, username, email, profile).await?;
         let key = id_key(&user.idp_issuer, &user.idp_subject);
         let arc_user = Arc::new(user);
         self.cache.insert(key, arc_user.clone()).await;
         Ok(arc_user)
     }
 
+    pub async fn evict_user(&self, issuer: &str, subject: &str) {
+        self.cache.invalidate(&id_key(issuer, subject)).await;
+    }
+
     pub async fn remove_user_from_cache_and_db(&self, issuer: &str, subject: &str) -> Result<(), sqlx::Error> {
         let key = id_key(issuer, subject);
         self.cache.invalidate(&key).await;
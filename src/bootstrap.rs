@@ -19,6 +19,10 @@ use crate::shared::middleware::apply_security_layers;
 use crate::infrastructure::web::routes::create_router;
 use tracing_subscriber::{fmt, EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 use crate::infrastructure::web::client::build_http_client;
+use crate::application::user_service::ManagedIssuer;
+use crate::infrastructure::config::OidcProviderKind;
+use crate::infrastructure::oidc::providers::build_registry;
+use crate::infrastructure::persistence::PgOidcMetadataRepo;
 use crate::infrastructure::oidc::providers::zitadel::admin::ZitadelAdminApi;
 
 async fn shutdown_signal() {
@@ -38,25 +42,6 @@ fn format_display_addr(addr: &SocketAddr) -> String {
     }
 }
 
-async fn wait_for_oidc_issuer(http_client: &Client, cfg: &Config) -> anyhow::Result<()> {
-    let url = format!(
-        "{}/.well-known/openid-configuration",
-        cfg.issuer_url.trim_end_matches('/')
-    );
-    loop {
-        match http_client.get(url.as_str()).send().await {
-            Ok(resp) if resp.status().is_success() => {
-                info!("OIDC issuer reachable");
-                return Ok(());
-            }
-            _ => {
-                warn!("OIDC issuer not ready, retry in 2 s …");
-                tokio::time::sleep(Duration::from_secs(2)).await;
-            }
-        }
-    }
-}
-
 pub fn init_tracing(cfg_filter: Option<&str>) {
     let filter = if std::env::var_os("RUST_LOG").is_some() {
         EnvFilter::try_from_default_env()
@@ -102,49 +87,57 @@ pub async fn run() -> anyhow::Result<()> {
         Duration::from_secs(15),       // request timeout
     )?;
 
-    wait_for_oidc_issuer(&http_client, &cfg).await?;
+    // Providers whose issuer is unreachable start from their persisted metadata, or degraded,
+    // and keep retrying in the background
+    let metadata_store = Arc::new(PgOidcMetadataRepo::new(Arc::new(pool.clone())));
+    let providers = Arc::new(build_registry(http_client.clone(), &cfg, metadata_store).await?);
+
+    let mut management_clients = Vec::new();
+    for pc in &cfg.providers {
+        let Some(service_token) = &pc.service_token else { continue };
+        if pc.kind != OidcProviderKind::Zitadel {
+            warn!(provider = %pc.name, "Service token configured for a non-ZITADEL provider, ignoring");
+            continue;
+        }
 
-    let provider = std::sync::Arc::new(
-        crate::infrastructure::oidc::providers::zitadel::provider::ZitadelProvider::new(
-            http_client.clone(),
-            &cfg.issuer_url,
-            &cfg.client_id,
-            &cfg.redirect_url,
-            &cfg.scopes,
-        ).await?
-    );
-
-    let management_client = if let Some(service_token) = &cfg.zitadel_service_token {
         match ZitadelAdminApi::new(
             http_client.clone(),
-            &cfg.issuer_url,
+            &pc.issuer_url,
             service_token.clone()
         ) {
             Ok(client) => {
-                info!("ZITADEL Admin API client initialized");
-                Some(Arc::new(Mutex::new(client)))
+                info!(provider = %pc.name, "ZITADEL Admin API client initialized");
+                // Users are keyed by the issuer from the discovery document, as found in tokens
+                let issuer = providers.get(Some(&pc.name))?.provider.issuer().to_string();
+                management_clients.push(ManagedIssuer {
+                    issuer,
+                    client: Arc::new(Mutex::new(client)),
+                });
             }
             Err(e) => {
-                warn!("Failed to initialize ZITADEL Admin API: {}", e);
+                warn!(provider = %pc.name, "Failed to initialize ZITADEL Admin API: {}", e);
                 warn!("User sync will only refresh from database");
-                None
             }
         }
-    } else {
-        info!("ZITADEL Admin API not configured - user sync will only refresh from database");
-        None
-    };
+    }
 
+    if management_clients.is_empty() {
+        info!("ZITADEL Admin API not configured - user sync will only refresh from database");
+    }
+    let sync_enabled = !management_clients.is_empty();
 
-    // Cast management client to trait object
-    let management_client_trait: Option<Arc<Mutex<dyn crate::infrastructure::oidc::provider::OidcAdminApi + Send + Sync>>> =
-        management_client.map(|mc| mc as Arc<Mutex<dyn crate::infrastructure::oidc::provider::OidcAdminApi + Send + Sync>>);
+    let state = AppState::new(cfg.clone(), pool.clone(), providers, http_client, management_clients)
+        .context("building application state")?;
 
-    let state = AppState::new(cfg.clone(), pool.clone(), provider.clone(), http_client, management_client_trait);
+    if let Some(sessions) = state.session_service.clone() {
+        info!("Server-side sessions enabled - starting session cleanup job");
+        tokio::spawn(crate::application::session_service::session_cleanup_loop(sessions));
+    }
+    tokio::spawn(crate::application::refresh_token_service::refresh_token_cleanup_loop(state.refresh_token_service.clone()));
 
-    // Start user sync task only if ZITADEL service key is configured
-    if cfg.zitadel_service_token.is_some() {
-        info!("ZITADEL_SERVICE_TOKEN configured - starting user sync job");
+    // Start user sync task only if a ZITADEL service key is configured
+    if sync_enabled {
+        info!("ZITADEL service token configured - starting user sync job");
         tokio::spawn({
             let state = state.clone();
             async move {
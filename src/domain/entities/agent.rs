@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Machine principal (service user / client credentials), kept apart from human `User`s.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct Agent {
+    pub id: uuid::Uuid,
+    pub idp_issuer: String,
+    pub idp_subject: String,
+    pub name: String,
+    pub client_id: Option<String>,
+    pub last_seen_at: Option<time::OffsetDateTime>,
+    pub created_at: time::OffsetDateTime,
+    pub updated_at: time::OffsetDateTime,
+}
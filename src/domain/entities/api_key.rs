@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// Personal API key. Only a SHA-256 hash of the secret part is stored; `prefix`
+/// is the public part used to look the key up.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct ApiKey {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    /// Roles the key may act with (a subset of the owner's roles at creation)
+    pub scopes: Vec<String>,
+    pub expires_at: Option<time::OffsetDateTime>,
+    pub last_used_at: Option<time::OffsetDateTime>,
+    pub revoked_at: Option<time::OffsetDateTime>,
+    pub created_at: time::OffsetDateTime,
+    pub updated_at: time::OffsetDateTime,
+}
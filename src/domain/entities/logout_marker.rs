@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Record of a back-channel logout. Any token of the provider session `sid` or, for a
+/// logout without `sid`, tokens for the subject issued before `logged_out_at` are
+/// rejected until the marker expires.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct LogoutMarker {
+    pub id: uuid::Uuid,
+    pub issuer: String,
+    pub subject: Option<String>,
+    pub sid: Option<String>,
+    pub logged_out_at: time::OffsetDateTime,
+    pub expires_at: time::OffsetDateTime,
+}
@@ -1,5 +1,19 @@
 pub mod user;
 pub mod role;
+pub mod session;
+pub mod logout_marker;
+pub mod agent;
+pub mod api_key;
+pub mod refresh_token;
+pub mod security_event;
+pub mod oidc_metadata_snapshot;
 
-pub use user::User;
-pub use role::Role;
\ No newline at end of file
+pub use user::{User, UserProfile};
+pub use role::Role;
+pub use session::Session;
+pub use logout_marker::LogoutMarker;
+pub use agent::Agent;
+pub use api_key::ApiKey;
+pub use refresh_token::{RefreshTokenFamily, RefreshTokenRecord};
+pub use security_event::SecurityEvent;
+pub use oidc_metadata_snapshot::OidcMetadataSnapshot;
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Last successfully fetched discovery document and JWKS of a provider, used to validate
+/// tokens when the issuer is unreachable at boot. Not trusted after `expires_at`.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct OidcMetadataSnapshot {
+    /// Configured issuer URL (the issuer identifier may not be known before discovery)
+    pub issuer_url: String,
+    pub discovery: serde_json::Value,
+    pub jwks: serde_json::Value,
+    pub fetched_at: time::OffsetDateTime,
+    pub expires_at: time::OffsetDateTime,
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// Chain of refresh tokens created by rotating one original token.
+/// `current_token` holds the AES-GCM encrypted latest token, when an encryption key is configured.
+/// `issuer`, `subject` and `sid` identify the login, so a back-channel logout can revoke it.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct RefreshTokenFamily {
+    pub id: uuid::Uuid,
+    pub provider: String,
+    pub current_token: Option<Vec<u8>>,
+    pub revoked_at: Option<time::OffsetDateTime>,
+    pub created_at: time::OffsetDateTime,
+    pub updated_at: time::OffsetDateTime,
+    pub issuer: Option<String>,
+    pub subject: Option<String>,
+    pub sid: Option<String>,
+}
+
+/// One member of a family, identified by the SHA-256 of the token.
+/// `rotated_at` is set once the token has been exchanged for a successor.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct RefreshTokenRecord {
+    pub token_hash: String,
+    pub family_id: uuid::Uuid,
+    pub rotated_at: Option<time::OffsetDateTime>,
+    pub created_at: time::OffsetDateTime,
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// Audit record of a security-relevant incident (e.g. refresh token reuse).
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct SecurityEvent {
+    pub id: uuid::Uuid,
+    pub kind: String,
+    pub provider: Option<String>,
+    pub subject: Option<String>,
+    pub details: serde_json::Value,
+    pub created_at: time::OffsetDateTime,
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// Server-side login session (backend-for-frontend mode).
+/// Token columns hold AES-GCM ciphertext, never plain tokens.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct Session {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub provider: String,
+    /// Provider session id (`sid` claim), matched by back-channel logout
+    pub idp_sid: Option<String>,
+    pub access_token: Vec<u8>,
+    pub refresh_token: Option<Vec<u8>>,
+    pub id_token: Option<Vec<u8>>,
+    pub expires_at: time::OffsetDateTime,
+    pub revoked_at: Option<time::OffsetDateTime>,
+    pub created_at: time::OffsetDateTime,
+    pub updated_at: time::OffsetDateTime,
+}
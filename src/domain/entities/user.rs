@@ -9,4 +9,42 @@ pub struct User {
     pub email: String,
     pub created_at: time::OffsetDateTime,
     pub updated_at: time::OffsetDateTime,
-}
\ No newline at end of file
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+    pub locale: Option<String>,
+    pub email_verified: Option<bool>,
+    /// Organization the user belongs to at the provider (e.g. ZITADEL resource owner)
+    pub org_id: Option<String>,
+    /// Roles of the latest login; `None` if the user hasn't signed in since roles were recorded
+    pub roles: Option<Vec<String>>,
+}
+
+/// Optional profile fields from the provider. `None` keeps the stored value on upsert.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct UserProfile {
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+    pub locale: Option<String>,
+    pub email_verified: Option<bool>,
+    pub org_id: Option<String>,
+    pub roles: Option<Vec<String>>,
+}
+
+impl User {
+    pub fn profile(&self) -> UserProfile {
+        UserProfile {
+            name: self.name.clone(),
+            given_name: self.given_name.clone(),
+            family_name: self.family_name.clone(),
+            picture: self.picture.clone(),
+            locale: self.locale.clone(),
+            email_verified: self.email_verified,
+            org_id: self.org_id.clone(),
+            roles: self.roles.clone(),
+        }
+    }
+}
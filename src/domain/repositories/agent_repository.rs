@@ -0,0 +1,17 @@
+use async_trait::async_trait;
+use crate::domain::entities::Agent;
+
+#[async_trait]
+pub trait AgentRepository: Send + Sync {
+    async fn find_by_subject(&self, issuer: &str, subject: &str) -> Result<Option<Agent>, sqlx::Error>;
+    /// Insert or update an agent. `seen` also bumps `last_seen_at` (the agent authenticated).
+    async fn upsert_agent(
+        &self,
+        issuer: &str,
+        subject: &str,
+        name: &str,
+        client_id: Option<&str>,
+        seen: bool,
+    ) -> Result<Agent, sqlx::Error>;
+    async fn get_all_agents(&self) -> Result<Vec<Agent>, sqlx::Error>;
+}
@@ -0,0 +1,16 @@
+use async_trait::async_trait;
+use crate::domain::entities::ApiKey;
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create(&self, key: &ApiKey) -> Result<ApiKey, sqlx::Error>;
+    /// Keys of a user that are not revoked (expired keys included).
+    async fn list_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<ApiKey>, sqlx::Error>;
+    /// Returns the key only if it is neither revoked nor expired.
+    async fn find_active_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>, sqlx::Error>;
+    async fn rename(&self, user_id: uuid::Uuid, id: uuid::Uuid, name: &str) -> Result<Option<ApiKey>, sqlx::Error>;
+    /// Returns `false` if the user has no such (unrevoked) key.
+    async fn revoke(&self, user_id: uuid::Uuid, id: uuid::Uuid) -> Result<bool, sqlx::Error>;
+    /// Record a use; throttled to one write per minute per key.
+    async fn touch_last_used(&self, id: uuid::Uuid) -> Result<(), sqlx::Error>;
+}
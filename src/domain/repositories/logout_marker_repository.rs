@@ -0,0 +1,18 @@
+use async_trait::async_trait;
+use crate::domain::entities::LogoutMarker;
+
+#[async_trait]
+pub trait LogoutMarkerRepository: Send + Sync {
+    async fn create(&self, marker: &LogoutMarker) -> Result<(), sqlx::Error>;
+    /// Most recent unexpired logout of a subject.
+    async fn latest_for_subject(&self, issuer: &str, subject: &str) -> Result<Option<time::OffsetDateTime>, sqlx::Error>;
+    /// Whether the provider session `sid` has an unexpired logout marker.
+    async fn exists_for_sid(&self, issuer: &str, sid: &str) -> Result<bool, sqlx::Error>;
+    /// Record the `jti` of a processed logout token until `expires_at`.
+    /// Returns `false` if it is already recorded and hasn't expired, i.e. the token is replayed.
+    async fn record_token_id(&self, issuer: &str, jti: &str, expires_at: time::OffsetDateTime) -> Result<bool, sqlx::Error>;
+    /// Forget a recorded `jti`, so a logout that failed to apply can be retried.
+    async fn forget_token_id(&self, issuer: &str, jti: &str) -> Result<(), sqlx::Error>;
+    /// Delete expired markers and token ids.
+    async fn delete_expired(&self) -> Result<u64, sqlx::Error>;
+}
@@ -1,5 +1,21 @@
+pub mod session_repository;
+pub mod logout_marker_repository;
+pub mod agent_repository;
+pub mod api_key_repository;
+pub mod refresh_token_repository;
+pub mod security_event_repository;
+pub mod oidc_metadata_repository;
+
+pub use session_repository::SessionRepository;
+pub use logout_marker_repository::LogoutMarkerRepository;
+pub use agent_repository::AgentRepository;
+pub use api_key_repository::ApiKeyRepository;
+pub use refresh_token_repository::RefreshTokenRepository;
+pub use security_event_repository::SecurityEventRepository;
+pub use oidc_metadata_repository::OidcMetadataRepository;
+
 use async_trait::async_trait;
-use crate::domain::entities::User;
+use crate::domain::entities::{User, UserProfile};
 use crate::shared::errors::service_error::ServiceError;
 
 #[async_trait]
@@ -10,7 +26,10 @@ pub trait UserRepository: Send + Sync {
     async fn save(&self, user: &User) -> Result<User, ServiceError>;
     async fn update(&self, user: &User) -> Result<User, ServiceError>;
     async fn delete(&self, id: uuid::Uuid) -> Result<(), ServiceError>;
-    async fn upsert_user(&self, issuer: &str, subject: &str, username: &str, email: &str) -> Result<User, sqlx::Error>;
+    /// Insert or update a user; profile fields that are `None` keep their stored value.
+    async fn upsert_user(&self, issuer: &str, subject: &str, username: &str, email: &str, profile: &UserProfile) -> Result<User, sqlx::Error>;
     async fn delete_by_subject(&self, issuer: &str, subject: &str) -> Result<(), sqlx::Error>;
+    /// Record the roles of the user's latest authenticated request.
+    async fn update_roles(&self, id: uuid::Uuid, roles: &[String]) -> Result<(), sqlx::Error>;
     async fn get_all_users(&self) -> Result<Vec<User>, sqlx::Error>;
 }
\ No newline at end of file
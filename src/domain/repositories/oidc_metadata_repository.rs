@@ -0,0 +1,10 @@
+use async_trait::async_trait;
+use crate::domain::entities::OidcMetadataSnapshot;
+
+#[async_trait]
+pub trait OidcMetadataRepository: Send + Sync {
+    /// Insert or replace the snapshot for `snapshot.issuer_url`.
+    async fn save(&self, snapshot: &OidcMetadataSnapshot) -> Result<(), sqlx::Error>;
+    /// The snapshot for an issuer URL, unless it has expired.
+    async fn find_valid(&self, issuer_url: &str) -> Result<Option<OidcMetadataSnapshot>, sqlx::Error>;
+}
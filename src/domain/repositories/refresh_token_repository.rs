@@ -0,0 +1,29 @@
+use async_trait::async_trait;
+use crate::domain::entities::{RefreshTokenFamily, RefreshTokenRecord};
+
+#[async_trait]
+pub trait RefreshTokenRepository: Send + Sync {
+    /// Start a family with its first token.
+    async fn create_family(&self, family: &RefreshTokenFamily, token_hash: &str) -> Result<(), sqlx::Error>;
+    async fn find_family(&self, id: uuid::Uuid) -> Result<Option<RefreshTokenFamily>, sqlx::Error>;
+    async fn find_token(&self, token_hash: &str) -> Result<Option<RefreshTokenRecord>, sqlx::Error>;
+    /// Mark `old_hash` rotated and add `new_hash` as the family's current token.
+    /// Returns `false` if `old_hash` had already been rotated.
+    async fn rotate(
+        &self,
+        family_id: uuid::Uuid,
+        old_hash: &str,
+        new_hash: &str,
+        current_token: Option<&[u8]>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn revoke_family(&self, id: uuid::Uuid) -> Result<(), sqlx::Error>;
+    /// Record the login of a family tracked before families recorded it.
+    async fn bind_login(&self, id: uuid::Uuid, issuer: &str, subject: &str, sid: Option<&str>) -> Result<(), sqlx::Error>;
+    /// When the window for adopting refresh tokens issued before login tracking opened.
+    async fn adoption_started_at(&self) -> Result<Option<time::OffsetDateTime>, sqlx::Error>;
+    /// Revoke the live families of a login: the families of provider session `sid` if given,
+    /// otherwise every family of `subject`. Returns the number of families revoked.
+    async fn revoke_for_login(&self, issuer: &str, subject: Option<&str>, sid: Option<&str>) -> Result<u64, sqlx::Error>;
+    /// Delete families not used since `before`.
+    async fn delete_stale(&self, before: time::OffsetDateTime) -> Result<u64, sqlx::Error>;
+}
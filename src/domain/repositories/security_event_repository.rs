@@ -0,0 +1,7 @@
+use async_trait::async_trait;
+use crate::domain::entities::SecurityEvent;
+
+#[async_trait]
+pub trait SecurityEventRepository: Send + Sync {
+    async fn record(&self, event: &SecurityEvent) -> Result<(), sqlx::Error>;
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use crate::domain::entities::Session;
+
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn create(&self, session: &Session) -> Result<Session, sqlx::Error>;
+    /// Returns the session only if it is neither revoked nor expired.
+    async fn find_active(&self, id: uuid::Uuid) -> Result<Option<Session>, sqlx::Error>;
+    /// `None` for refresh or ID token keeps the stored value.
+    async fn update_tokens(
+        &self,
+        id: uuid::Uuid,
+        access_token: &[u8],
+        refresh_token: Option<&[u8]>,
+        id_token: Option<&[u8]>,
+    ) -> Result<(), sqlx::Error>;
+    async fn revoke(&self, id: uuid::Uuid) -> Result<(), sqlx::Error>;
+    async fn revoke_for_user(&self, user_id: uuid::Uuid) -> Result<u64, sqlx::Error>;
+    async fn revoke_by_idp_sid(&self, provider: &str, sid: &str) -> Result<u64, sqlx::Error>;
+    async fn delete_expired(&self) -> Result<u64, sqlx::Error>;
+}
@@ -1,7 +1,241 @@
+use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
 use anyhow::Context;
 use dotenvy::dotenv;
 use serde::Deserialize;
+use crate::infrastructure::oidc::jwk::JwksSettings;
+use crate::infrastructure::oidc::validation::ValidationPolicy;
+
+/// Which `OidcProvider` implementation to use for the configured issuer.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OidcProviderKind {
+    Zitadel,
+    Generic,
+}
+
+impl std::str::FromStr for OidcProviderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "zitadel" => Ok(Self::Zitadel),
+            "generic" | "oidc" => Ok(Self::Generic),
+            other => Err(anyhow::anyhow!("unknown OIDC provider kind: {other}")),
+        }
+    }
+}
+
+/// How the backend authenticates to the provider's token, revocation and introspection endpoints.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAuthMethod {
+    None,
+    ClientSecretBasic,
+    ClientSecretPost,
+    PrivateKeyJwt,
+}
+
+impl std::str::FromStr for ClientAuthMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "client_secret_basic" => Ok(Self::ClientSecretBasic),
+            "client_secret_post" => Ok(Self::ClientSecretPost),
+            "private_key_jwt" => Ok(Self::PrivateKeyJwt),
+            other => Err(anyhow::anyhow!("unknown client auth method: {other}")),
+        }
+    }
+}
+
+/// Where the browser session's tokens live.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionMode {
+    /// Access and refresh tokens in HttpOnly cookies
+    Cookie,
+    /// Backend-for-frontend: opaque signed session id cookie, tokens stored server-side
+    Server,
+}
+
+impl std::str::FromStr for SessionMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "cookie" => Ok(Self::Cookie),
+            "server" | "bff" => Ok(Self::Server),
+            other => Err(anyhow::anyhow!("unknown session mode: {other}")),
+        }
+    }
+}
+
+/// Settings for one trusted OIDC provider.
+#[derive(Deserialize, Clone)]
+pub struct OidcProviderConfig {
+    /// Short name used in `/api/auth/login?provider=...`
+    pub name: String,
+    pub kind: OidcProviderKind,
+    pub issuer_url: String,
+    pub client_id: String,
+    pub redirect_url: String,
+    pub scopes: String,
+    pub roles_claim: Option<String>,
+    pub client_auth_method: ClientAuthMethod,
+    pub client_secret: Option<String>,
+    /// PEM or ZITADEL JSON key file for `private_key_jwt`
+    pub client_key_file: Option<String>,
+    pub client_key_id: Option<String>,
+    pub client_assertion_alg: String,
+    /// Where the provider sends the browser after RP-initiated logout
+    pub post_logout_redirect_uri: Option<String>,
+    /// ZITADEL service token for the user sync job (ZITADEL providers only)
+    pub service_token: Option<String>,
+    /// Token validation rules: the global policy with this issuer's overrides applied
+    pub validation: ValidationPolicy,
+    /// Whether opaque bearer tokens are sent to this provider's introspection endpoint
+    pub introspect_opaque: bool,
+}
+
+impl OidcProviderConfig {
+    /// Read a provider from `{prefix}ISSUER_URL`, `{prefix}CLIENT_ID`, ... falling back to the
+    /// shared `OIDC_REDIRECT_URL` / `OIDC_SCOPES` for named providers. Opaque tokens are only
+    /// introspected at the default provider unless `{prefix}INTROSPECT_OPAQUE` says otherwise.
+    fn from_env(name: &str, prefix: &str, validation: &ValidationPolicy, is_default: bool) -> Result<Self, anyhow::Error> {
+        let var = |key: &str| env::var(format!("{prefix}{key}"));
+
+        let issuer_url = var("ISSUER_URL")
+            .with_context(|| format!("{prefix}ISSUER_URL must be set"))?;
+        let client_id = var("CLIENT_ID")
+            .with_context(|| format!("{prefix}CLIENT_ID must be set"))?;
+        let redirect_url = var("REDIRECT_URL")
+            .or_else(|_| env::var("OIDC_REDIRECT_URL"))
+            .with_context(|| format!("{prefix}REDIRECT_URL or OIDC_REDIRECT_URL must be set"))?;
+        let scopes = var("SCOPES")
+            .or_else(|_| env::var("OIDC_SCOPES"))
+            .with_context(|| format!("{prefix}SCOPES or OIDC_SCOPES must be set"))?;
+        let kind = var("PROVIDER")
+            .unwrap_or_else(|_| "zitadel".to_string())
+            .parse::<OidcProviderKind>()
+            .with_context(|| format!("{prefix}PROVIDER must be 'zitadel' or 'generic'"))?;
+        let roles_claim = var("ROLES_CLAIM").ok()
+            .filter(|s| !s.trim().is_empty());
+
+        let client_secret = var("CLIENT_SECRET").ok()
+            .filter(|s| !s.is_empty());
+        let client_key_file = var("CLIENT_KEY_FILE").ok()
+            .filter(|s| !s.is_empty());
+        let client_key_id = var("CLIENT_KEY_ID").ok()
+            .filter(|s| !s.is_empty());
+        let client_assertion_alg = var("CLIENT_ASSERTION_ALG")
+            .unwrap_or_else(|_| "RS256".to_string());
+        let post_logout_redirect_uri = var("POST_LOGOUT_REDIRECT_URI")
+            .or_else(|_| env::var("OIDC_POST_LOGOUT_REDIRECT_URI"))
+            .or_else(|_| env::var("FRONTEND_URL"))
+            .ok()
+            .filter(|s| !s.is_empty());
+        // Without an explicit method, infer it from the credentials that are present
+        let client_auth_method = match var("CLIENT_AUTH_METHOD") {
+            Ok(method) => method.parse::<ClientAuthMethod>()
+                .with_context(|| format!("{prefix}CLIENT_AUTH_METHOD must be none, client_secret_basic, client_secret_post or private_key_jwt"))?,
+            Err(_) if client_key_file.is_some() => ClientAuthMethod::PrivateKeyJwt,
+            Err(_) if client_secret.is_some() => ClientAuthMethod::ClientSecretBasic,
+            Err(_) => ClientAuthMethod::None,
+        };
+        let introspect_opaque = match var("INTROSPECT_OPAQUE") {
+            Ok(value) => value.trim().parse::<bool>()
+                .with_context(|| format!("{prefix}INTROSPECT_OPAQUE must be true or false"))?,
+            Err(_) => is_default,
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+            issuer_url,
+            client_id,
+            redirect_url,
+            scopes,
+            roles_claim,
+            client_auth_method,
+            client_secret,
+            client_key_file,
+            client_key_id,
+            client_assertion_alg,
+            post_logout_redirect_uri,
+            service_token: None,
+            validation: read_validation_policy(prefix, validation)?,
+            introspect_opaque,
+        })
+    }
+}
+
+/// Read `{prefix}TOKEN_LEEWAY_SECS`, `{prefix}TOKEN_MAX_AGE_SECS` (0 disables the check),
+/// `{prefix}TOKEN_REQUIRED_CLAIMS`, `{prefix}TOKEN_AUDIENCES` and `{prefix}TOKEN_AUTHORIZED_PARTIES`
+/// (comma separated) on top of `defaults`.
+fn read_validation_policy(prefix: &str, defaults: &ValidationPolicy) -> Result<ValidationPolicy, anyhow::Error> {
+    let var = |key: &str| env::var(format!("{prefix}{key}")).ok().filter(|s| !s.trim().is_empty());
+    let list = |value: String| value.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let mut policy = defaults.clone();
+    if let Some(leeway) = var("TOKEN_LEEWAY_SECS") {
+        policy.leeway_secs = leeway.parse()
+            .with_context(|| format!("{prefix}TOKEN_LEEWAY_SECS must be a positive integer"))?;
+    }
+    if let Some(max_age) = var("TOKEN_MAX_AGE_SECS") {
+        let max_age: u64 = max_age.parse()
+            .with_context(|| format!("{prefix}TOKEN_MAX_AGE_SECS must be a positive integer"))?;
+        policy.max_age_secs = (max_age > 0).then_some(max_age);
+    }
+    if let Some(claims) = var("TOKEN_REQUIRED_CLAIMS") {
+        policy.required_claims = list(claims);
+    }
+    if let Some(audiences) = var("TOKEN_AUDIENCES") {
+        policy.audiences = list(audiences);
+    }
+    if let Some(parties) = var("TOKEN_AUTHORIZED_PARTIES") {
+        policy.authorized_parties = list(parties);
+    }
+    Ok(policy)
+}
+
+/// Read a duration in seconds that must be at least 1: a zero interval would turn a refresh
+/// loop into a busy loop against the provider.
+fn read_interval_secs(var: &str, default: u64) -> Result<u64, anyhow::Error> {
+    let secs = env::var(var)
+        .unwrap_or_else(|_| default.to_string())
+        .parse::<u64>()
+        .with_context(|| format!("{var} must be a positive integer"))?;
+    if secs == 0 {
+        anyhow::bail!("{var} must be a positive integer");
+    }
+    Ok(secs)
+}
+
+fn read_service_token(token_var: &str, path_var: &str) -> Option<String> {
+    env::var(token_var).ok()
+        .or_else(|| {
+            env::var(path_var).ok()
+                .and_then(|path| {
+                    match std::fs::read_to_string(&path) {
+                        Ok(content) => {
+                            tracing::info!("Loaded ZITADEL service token from {}", path);
+                            Some(content.trim().to_string())
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to load service key from {}: {}", path, e);
+                            None
+                        }
+                    }
+                })
+        })
+}
 
 #[derive(Deserialize, Clone)]
 pub struct Config {
@@ -12,12 +246,37 @@ pub struct Config {
     pub log_filter: String,
     pub allowed_origin: String,
 
-    pub issuer_url: String,
-    pub client_id: String,
-    pub redirect_url: String,
-    pub scopes: String,
+    /// Where the browser lands after login and logout when no `return_to` was requested
+    pub frontend_url: String,
+    /// Origins, optionally with a path prefix, that `return_to` may point at
+    pub return_to_allowlist: Vec<String>,
+
+    /// Trusted providers; the first one is the default for the browser login.
+    pub providers: Vec<OidcProviderConfig>,
+
+    pub discovery_refresh_interval_secs: u64,
+    pub jwks_refresh_interval_secs: u64,
+    pub jwks_min_refetch_interval_secs: u64,
+    pub jwks_key_grace_secs: u64,
+    /// How long the persisted discovery document and JWKS may stand in for an unreachable issuer
+    pub metadata_snapshot_max_age_secs: u64,
+
+    /// Global token validation policy; providers may override it (`OIDC_<NAME>_TOKEN_*`)
+    pub token_validation: ValidationPolicy,
+
+    pub session_mode: SessionMode,
+    pub session_secret: Option<String>,
+    pub session_encryption_key: Option<String>,
+    pub session_ttl_secs: u64,
+
+    /// External base URL of the API (scheme, host and path prefix), used to check DPoP `htu`.
+    /// DPoP requests are rejected when unset.
+    pub public_url: Option<String>,
+    pub dpop_proof_max_age_secs: u64,
+
+    /// Downstream services reachable through token exchange: name -> audience
+    pub downstream_services: HashMap<String, String>,
 
-    pub zitadel_service_token: Option<String>,
     pub environment: String,
 }
 
@@ -29,6 +288,16 @@ impl Config {
     pub fn is_development(&self) -> bool {
         self.environment.to_lowercase() == "development"
     }
+
+    pub fn jwks_settings(&self) -> JwksSettings {
+        JwksSettings {
+            discovery_refresh_interval: Duration::from_secs(self.discovery_refresh_interval_secs),
+            refresh_interval: Duration::from_secs(self.jwks_refresh_interval_secs),
+            min_refetch_interval: Duration::from_secs(self.jwks_min_refetch_interval_secs),
+            key_grace_period: Duration::from_secs(self.jwks_key_grace_secs),
+            snapshot_max_age: Duration::from_secs(self.metadata_snapshot_max_age_secs),
+        }
+    }
 }
 
 impl Config {
@@ -43,14 +312,6 @@ impl Config {
             .unwrap_or_else(|_| "3000".into())
             .parse::<u16>()
             .context("PORT must be a valid port number")?;
-        let issuer_url = env::var("OIDC_ISSUER_URL")
-            .context("OIDC_ISSUER_URL must be set")?;
-        let client_id = env::var("OIDC_CLIENT_ID")
-            .context("OIDC_CLIENT_ID must be set")?;
-        let redirect_url = env::var("OIDC_REDIRECT_URL")
-            .context("OIDC_REDIRECT_URL must be set")?;
-        let scopes = env::var("OIDC_SCOPES")
-            .context("OIDC_SCOPES must be set")?;
 
         let db_max_connections = env::var("DB_MAX_CONNECTIONS")
             .unwrap_or_else(|_| "5".to_string())
@@ -63,22 +324,106 @@ impl Config {
         let environment = env::var("ENVIRONMENT")
             .unwrap_or_else(|_| "development".to_string());
 
-        let zitadel_service_token = std::env::var("ZITADEL_SERVICE_TOKEN").ok()
-            .or_else(|| {
-                std::env::var("ZITADEL_SERVICE_TOKEN_PATH").ok()
-                    .and_then(|path| {
-                        match std::fs::read_to_string(&path) {
-                            Ok(content) => {
-                                tracing::info!("Loaded ZITADEL service token from {}", path);
-                                Some(content.trim().to_string())
-                            }
-                            Err(e) => {
-                                tracing::warn!("Failed to load service key from {}: {}", path, e);
-                                None
-                            }
-                        }
-                    })
-            });
+        let frontend_url = env::var("FRONTEND_URL")
+            .unwrap_or_else(|_| "http://localhost:5173".to_string());
+        url::Url::parse(&frontend_url)
+            .context("FRONTEND_URL must be an absolute URL")?;
+        // RETURN_TO_ALLOWLIST=https://app.home.arpa,https://nas.home.arpa/files/
+        let mut return_to_allowlist = env::var("RETURN_TO_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        if return_to_allowlist.is_empty() {
+            return_to_allowlist.push(frontend_url.clone());
+        }
+        for entry in &return_to_allowlist {
+            let parsed = url::Url::parse(entry)
+                .with_context(|| format!("RETURN_TO_ALLOWLIST entry '{entry}' must be an absolute URL"))?;
+            if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+                anyhow::bail!("RETURN_TO_ALLOWLIST entry '{entry}' must be an http(s) origin");
+            }
+        }
+
+        let token_validation = read_validation_policy("", &ValidationPolicy::default())?;
+
+        // Either a list of named providers (OIDC_PROVIDERS=home,work with OIDC_HOME_*, OIDC_WORK_*)
+        // or a single provider configured through the plain OIDC_* variables.
+        let providers = match env::var("OIDC_PROVIDERS").ok().filter(|s| !s.trim().is_empty()) {
+            Some(names) => names
+                .split(',')
+                .map(str::trim)
+                .filter(|n| !n.is_empty())
+                .enumerate()
+                .map(|(index, name)| {
+                    let prefix = format!("OIDC_{}_", name.to_uppercase().replace('-', "_"));
+                    let mut provider = OidcProviderConfig::from_env(name, &prefix, &token_validation, index == 0)?;
+                    provider.service_token = read_service_token(
+                        &format!("{prefix}SERVICE_TOKEN"),
+                        &format!("{prefix}SERVICE_TOKEN_PATH"),
+                    );
+                    Ok(provider)
+                })
+                .collect::<Result<Vec<_>, anyhow::Error>>()?,
+            None => {
+                let mut provider = OidcProviderConfig::from_env("default", "OIDC_", &token_validation, true)?;
+                provider.service_token = read_service_token("ZITADEL_SERVICE_TOKEN", "ZITADEL_SERVICE_TOKEN_PATH");
+                vec![provider]
+            }
+        };
+        if providers.is_empty() {
+            anyhow::bail!("OIDC_PROVIDERS must name at least one provider");
+        }
+
+        let discovery_refresh_interval_secs = read_interval_secs("DISCOVERY_REFRESH_INTERVAL_SECS", 3600)?;
+        let jwks_refresh_interval_secs = read_interval_secs("JWKS_REFRESH_INTERVAL_SECS", 3600)?;
+        let jwks_min_refetch_interval_secs = read_interval_secs("JWKS_MIN_REFETCH_INTERVAL_SECS", 30)?;
+        let jwks_key_grace_secs = read_interval_secs("JWKS_KEY_GRACE_SECS", 3600)?;
+        let metadata_snapshot_max_age_secs = read_interval_secs("OIDC_METADATA_MAX_AGE_SECS", 7 * 24 * 60 * 60)?;
+
+        let session_mode = env::var("SESSION_MODE")
+            .unwrap_or_else(|_| "cookie".to_string())
+            .parse::<SessionMode>()
+            .context("SESSION_MODE must be 'cookie' or 'server'")?;
+        let session_secret = env::var("SESSION_SECRET").ok()
+            .filter(|s| !s.is_empty());
+        let session_encryption_key = env::var("SESSION_ENCRYPTION_KEY").ok()
+            .filter(|s| !s.is_empty());
+        let session_ttl_secs = env::var("SESSION_TTL_SECS")
+            .unwrap_or_else(|_| (7 * 24 * 60 * 60).to_string())
+            .parse::<u64>()
+            .context("SESSION_TTL_SECS must be a positive integer")?;
+
+        let public_url = env::var("PUBLIC_URL").ok()
+            .map(|s| s.trim_end_matches('/').to_string())
+            .filter(|s| !s.is_empty());
+        let dpop_proof_max_age_secs = read_interval_secs("DPOP_PROOF_MAX_AGE_SECS", 60)?;
+
+        // TOKEN_EXCHANGE_SERVICES=nas=urn:hestix:nas,media=https://media.home.arpa
+        let downstream_services = env::var("TOKEN_EXCHANGE_SERVICES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|entry| {
+                entry.split_once('=')
+                    .map(|(name, audience)| (name.trim().to_string(), audience.trim().to_string()))
+                    .filter(|(name, audience)| !name.is_empty() && !audience.is_empty())
+                    .with_context(|| format!("TOKEN_EXCHANGE_SERVICES entry '{entry}' must be name=audience"))
+            })
+            .collect::<Result<HashMap<_, _>, anyhow::Error>>()?;
+
+        if session_mode == SessionMode::Server {
+            match &session_secret {
+                Some(secret) if secret.len() >= 32 => {}
+                _ => anyhow::bail!("SESSION_SECRET must be set (at least 32 characters) when SESSION_MODE=server"),
+            }
+            if session_encryption_key.is_none() {
+                anyhow::bail!("SESSION_ENCRYPTION_KEY must be set when SESSION_MODE=server");
+            }
+        }
 
         Ok(Config {
             database_url,
@@ -87,11 +432,22 @@ impl Config {
             port,
             log_filter,
             allowed_origin,
-            issuer_url,
-            client_id,
-            redirect_url,
-            scopes,
-            zitadel_service_token,
+            frontend_url,
+            return_to_allowlist,
+            providers,
+            discovery_refresh_interval_secs,
+            jwks_refresh_interval_secs,
+            jwks_min_refetch_interval_secs,
+            jwks_key_grace_secs,
+            metadata_snapshot_max_age_secs,
+            token_validation,
+            session_mode,
+            session_secret,
+            session_encryption_key,
+            session_ttl_secs,
+            public_url,
+            dpop_proof_max_age_secs,
+            downstream_services,
             environment,
         })
     }
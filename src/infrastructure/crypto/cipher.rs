@@ -0,0 +1,60 @@
+use base64::{engine::general_purpose, Engine as _};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::shared::errors::ServiceError;
+
+/// AES-256-GCM encryption for tokens stored at rest.
+///
+/// Output layout: `nonce (12 bytes) || ciphertext || tag`. The associated data
+/// binds a ciphertext to its owner (e.g. the session id), so blobs can't be
+/// swapped between rows.
+pub struct TokenCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl TokenCipher {
+    /// `key_b64` is a standard base64 encoded 32-byte key.
+    pub fn from_base64(key_b64: &str) -> Result<Self, ServiceError> {
+        let bytes = general_purpose::STANDARD
+            .decode(key_b64.trim())
+            .map_err(|e| ServiceError::Validation(format!("encryption key is not valid base64: {e}")))?;
+        let unbound = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| ServiceError::Validation("encryption key must be 32 bytes".into()))?;
+
+        Ok(Self { key: LessSafeKey::new(unbound), rng: SystemRandom::new() })
+    }
+
+    pub fn encrypt(&self, plaintext: &str, aad: &[u8]) -> Result<Vec<u8>, ServiceError> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce_bytes)
+            .map_err(|_| ServiceError::Internal("OS RNG failed".into()))?;
+
+        let mut in_out = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce_bytes), Aad::from(aad), &mut in_out)
+            .map_err(|_| ServiceError::Internal("token encryption failed".into()))?;
+
+        let mut out = Vec::with_capacity(NONCE_LEN + in_out.len());
+        out.extend_from_slice(&nonce_bytes);
+        out.extend_from_slice(&in_out);
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, blob: &[u8], aad: &[u8]) -> Result<String, ServiceError> {
+        if blob.len() < NONCE_LEN {
+            return Err(ServiceError::Internal("ciphertext too short".into()));
+        }
+        let (nonce_bytes, ciphertext) = blob.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce_bytes)
+            .map_err(|_| ServiceError::Internal("invalid nonce".into()))?;
+
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = self.key
+            .open_in_place(nonce, Aad::from(aad), &mut in_out)
+            .map_err(|_| ServiceError::Internal("token decryption failed".into()))?;
+
+        String::from_utf8(plaintext.to_vec())
+            .map_err(|_| ServiceError::Internal("decrypted token is not UTF-8".into()))
+    }
+}
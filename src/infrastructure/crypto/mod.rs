@@ -0,0 +1,5 @@
+pub mod cipher;
+pub mod signer;
+
+pub use cipher::TokenCipher;
+pub use signer::ValueSigner;
@@ -0,0 +1,26 @@
+use base64::{engine::general_purpose, Engine as _};
+use ring::hmac;
+
+/// HMAC-SHA256 signing for values handed to the browser (`<value>.<signature>`).
+pub struct ValueSigner {
+    key: hmac::Key,
+}
+
+impl ValueSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self { key: hmac::Key::new(hmac::HMAC_SHA256, secret) }
+    }
+
+    pub fn sign(&self, value: &str) -> String {
+        let tag = hmac::sign(&self.key, value.as_bytes());
+        format!("{}.{}", value, general_purpose::URL_SAFE_NO_PAD.encode(tag.as_ref()))
+    }
+
+    /// Return the original value if the signature matches (constant-time comparison).
+    pub fn verify<'a>(&self, signed: &'a str) -> Option<&'a str> {
+        let (value, signature) = signed.rsplit_once('.')?;
+        let tag = general_purpose::URL_SAFE_NO_PAD.decode(signature).ok()?;
+        hmac::verify(&self.key, value.as_bytes(), &tag).ok()?;
+        Some(value)
+    }
+}
@@ -1,4 +1,5 @@
 pub mod persistence;
 pub mod oidc;
 pub mod web;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod crypto;
\ No newline at end of file
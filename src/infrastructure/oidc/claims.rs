@@ -1,3 +1,15 @@
+use crate::domain::entities::{Role, UserProfile};
+use crate::infrastructure::oidc::error::OidcError;
+
+/// Whether a token was issued to a person or to a machine (client credentials).
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PrincipalKind {
+    #[default]
+    Human,
+    Machine,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct OidcClaims {
     pub exp: u64,
@@ -7,6 +19,174 @@ pub struct OidcClaims {
     pub sub: String,
     pub email: Option<String>,
     pub preferred_username: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+    pub locale: Option<String>,
+    pub email_verified: Option<bool>,
+    /// Organization of the user (`org_id`, or ZITADEL's resource owner)
+    pub org_id: Option<String>,
+    /// Provider session id, matched by back-channel logout
+    pub sid: Option<String>,
+    /// OAuth client the token was issued to (`client_id`, or `azp`)
+    pub client_id: Option<String>,
+    pub principal: PrincipalKind,
+    /// Authentication context class reference, e.g. a level of assurance
+    pub acr: Option<String>,
+    /// Authentication methods used (RFC 8176: `pwd`, `otp`, `mfa`, ...)
+    #[serde(default)]
+    pub amr: Vec<String>,
+    /// Unix time the user last actively authenticated
+    pub auth_time: Option<u64>,
+    /// JWK thumbprint the token is bound to (`cnf.jkt`, DPoP)
+    pub cnf_jkt: Option<String>,
     pub roles: Vec<String>,
+    /// Every claim of the token (plus ID token / userinfo claims merged at login)
+    #[serde(default)]
+    pub raw: serde_json::Map<String, serde_json::Value>,
 }
 
+/// Profile claims that may be missing from an access token but present in
+/// the ID token or the userinfo response.
+const PROFILE_CLAIMS: &[&str] = &[
+    "email", "email_verified", "preferred_username", "name", "given_name",
+    "family_name", "picture", "locale", "org_id", ZITADEL_ORG_CLAIM,
+];
+
+/// ZITADEL: id of the organization that owns the user
+const ZITADEL_ORG_CLAIM: &str = "urn:zitadel:iam:user:resourceowner:id";
+
+
+impl OidcClaims {
+    /// Map a raw claim set (JWT payload or introspection response) into `OidcClaims`.
+    /// Roles are left empty; they are filled in by the provider's `RoleMapper`.
+    pub fn from_value(c: &serde_json::Value) -> Result<Self, OidcError> {
+        let exp = c.get("exp").and_then(|v| v.as_u64()).ok_or(OidcError::MissingClaim("exp"))?;
+        let iat = c.get("iat").and_then(|v| v.as_u64()).unwrap_or(0);
+        let iss = c.get("iss").and_then(|v| v.as_str()).ok_or(OidcError::MissingClaim("iss"))?.to_string();
+        let aud = match c.get("aud") {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(serde_json::Value::Array(arr)) => arr.first().and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+            _ => "".to_string()
+        };
+        let sub = c.get("sub").and_then(|v| v.as_str()).ok_or(OidcError::MissingClaim("sub"))?.to_string();
+        let raw = c.as_object().cloned().unwrap_or_default();
+        let sid = c.get("sid").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let client_id = c.get("client_id").or_else(|| c.get("azp"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        let acr = c.get("acr").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let amr = c.get("amr").and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        let auth_time = c.get("auth_time").and_then(|v| v.as_u64());
+        let cnf_jkt = c.pointer("/cnf/jkt").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        // Client credentials tokens: RFC 9068 sets `sub` to the client id, some providers add `gty`
+        let is_client_credentials = c.get("gty").and_then(|v| v.as_str()) == Some("client_credentials")
+            || c.get("client_id").and_then(|v| v.as_str()) == Some(sub.as_str());
+        let principal = if is_client_credentials { PrincipalKind::Machine } else { PrincipalKind::Human };
+
+        Ok(OidcClaims {
+            exp, iat, iss, aud, sub,
+            // Profile fields are read from `raw`
+            email: None, preferred_username: None, name: None, given_name: None,
+            family_name: None, picture: None, locale: None, email_verified: None, org_id: None,
+            sid, client_id, principal,
+            acr, amr, auth_time, cnf_jkt,
+            roles: Vec::new(),
+            raw: serde_json::Map::new(),
+        }
+        .with_raw(raw))
+    }
+
+    /// Keep `raw` and read the profile fields from it.
+    fn with_raw(mut self, raw: serde_json::Map<String, serde_json::Value>) -> Self {
+        let text = |name: &str| raw.get(name).and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        self.email = text("email");
+        self.preferred_username = text("preferred_username");
+        self.name = text("name");
+        self.given_name = text("given_name");
+        self.family_name = text("family_name");
+        self.picture = text("picture");
+        self.locale = text("locale");
+        // Some providers send booleans as strings
+        self.email_verified = match raw.get("email_verified") {
+            Some(serde_json::Value::Bool(b)) => Some(*b),
+            Some(serde_json::Value::String(s)) => s.parse().ok(),
+            _ => None,
+        };
+        self.org_id = text("org_id").or_else(|| text(ZITADEL_ORG_CLAIM));
+        self.raw = raw;
+        self
+    }
+
+    /// Fill profile claims the token lacks from another claim set of the same subject
+    /// (validated ID token claims or a userinfo response).
+    pub fn merge_profile(self, other: &serde_json::Map<String, serde_json::Value>) -> Self {
+        let mut raw = self.raw.clone();
+        for name in PROFILE_CLAIMS {
+            if !raw.contains_key(*name)
+                && let Some(value) = other.get(*name)
+            {
+                raw.insert(name.to_string(), value.clone());
+            }
+        }
+        self.with_raw(raw)
+    }
+
+    /// Fill the session id and authentication context (`acr`, `amr`, `auth_time`) the token
+    /// lacks from the validated ID token of the same login; access tokens often omit them.
+    pub fn merge_authentication_context(&mut self, id_claims: &OidcClaims) {
+        if self.sid.is_none() {
+            self.sid = id_claims.sid.clone();
+        }
+        if self.acr.is_none() {
+            self.acr = id_claims.acr.clone();
+        }
+        if self.amr.is_empty() {
+            self.amr = id_claims.amr.clone();
+        }
+        if self.auth_time.is_none() {
+            self.auth_time = id_claims.auth_time;
+        }
+    }
+
+    /// Profile fields to store on the local user.
+    pub fn profile(&self) -> UserProfile {
+        UserProfile {
+            name: self.name.clone(),
+            given_name: self.given_name.clone(),
+            family_name: self.family_name.clone(),
+            picture: self.picture.clone(),
+            locale: self.locale.clone(),
+            email_verified: self.email_verified,
+            org_id: self.org_id.clone(),
+            roles: Some(self.roles.clone()),
+        }
+    }
+
+    /// Set the mapped roles. Holders of the `agent` role are machine principals
+    /// (e.g. ZITADEL service users), whatever grant their token came from.
+    pub fn with_roles(mut self, roles: Vec<String>) -> Self {
+        if roles.iter().any(|r| r == Role::Agent.as_str()) {
+            self.principal = PrincipalKind::Machine;
+        }
+        self.roles = roles;
+        self
+    }
+
+    pub fn is_machine(&self) -> bool {
+        self.principal == PrincipalKind::Machine
+    }
+
+    /// Display name for a machine principal.
+    pub fn agent_name(&self) -> String {
+        self.preferred_username.clone()
+            .or_else(|| self.client_id.clone())
+            .unwrap_or_else(|| self.sub.clone())
+    }
+}
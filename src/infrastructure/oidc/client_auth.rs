@@ -0,0 +1,174 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use base64::{engine::general_purpose, Engine as _};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::{Client, RequestBuilder};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use crate::infrastructure::config::{ClientAuthMethod, OidcProviderConfig};
+use crate::infrastructure::oidc::error::OidcError;
+
+const CLIENT_ASSERTION_TYPE: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// Lifetime of a `private_key_jwt` client assertion, in seconds.
+const CLIENT_ASSERTION_TTL_SECS: u64 = 60;
+
+/// How the client proves its identity to the provider.
+pub enum ClientCredential {
+    /// Public client (PKCE): only `client_id` is sent
+    None,
+    SecretBasic(String),
+    SecretPost(String),
+    PrivateKeyJwt {
+        key: EncodingKey,
+        key_id: Option<String>,
+        algorithm: Algorithm,
+    },
+}
+
+/// Client authentication for the token, revocation and introspection endpoints.
+pub struct ClientAuth {
+    client_id: String,
+    /// `aud` of client assertions (the issuer identifier)
+    audience: String,
+    credential: ClientCredential,
+}
+
+/// ZITADEL application key file (`{"type":"application","keyId":...,"key":...}`)
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyFile {
+    key_id: String,
+    key: String,
+}
+
+#[derive(Serialize)]
+struct ClientAssertionClaims<'a> {
+    iss: &'a str,
+    sub: &'a str,
+    aud: &'a str,
+    jti: String,
+    iat: u64,
+    exp: u64,
+}
+
+impl ClientAuth {
+    pub fn new(client_id: &str, audience: &str, credential: ClientCredential) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            audience: audience.to_string(),
+            credential,
+        }
+    }
+
+    /// Build the client authentication configured for a provider. `audience` is the issuer.
+    pub fn from_config(pc: &OidcProviderConfig, audience: &str) -> Result<Self, OidcError> {
+        let secret = || pc.client_secret.clone()
+            .ok_or_else(|| OidcError::Internal(format!("provider {}: client secret required for {:?}", pc.name, pc.client_auth_method)));
+
+        let credential = match pc.client_auth_method {
+            ClientAuthMethod::None => ClientCredential::None,
+            ClientAuthMethod::ClientSecretBasic => ClientCredential::SecretBasic(secret()?),
+            ClientAuthMethod::ClientSecretPost => ClientCredential::SecretPost(secret()?),
+            ClientAuthMethod::PrivateKeyJwt => {
+                let path = pc.client_key_file.as_deref()
+                    .ok_or_else(|| OidcError::Internal(format!("provider {}: key file required for private_key_jwt", pc.name)))?;
+                let algorithm = pc.client_assertion_alg.parse::<Algorithm>()
+                    .map_err(|e| OidcError::Internal(format!("invalid client assertion alg: {e}")))?;
+                let (key, key_id) = load_signing_key(path, algorithm)?;
+                ClientCredential::PrivateKeyJwt {
+                    key,
+                    key_id: pc.client_key_id.clone().or(key_id),
+                    algorithm,
+                }
+            }
+        };
+
+        Ok(Self::new(&pc.client_id, audience, credential))
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Build an authenticated form POST to one of the provider's endpoints.
+    pub fn form_post(
+        &self,
+        http_client: &Client,
+        endpoint: &str,
+        mut form: Vec<(&'static str, String)>,
+    ) -> Result<RequestBuilder, OidcError> {
+        let mut request = http_client.post(endpoint);
+
+        match &self.credential {
+            ClientCredential::None => {
+                form.push(("client_id", self.client_id.clone()));
+            }
+            ClientCredential::SecretBasic(secret) => {
+                // RFC 6749 §2.3.1: credentials are form-urlencoded before base64
+                request = request.basic_auth(form_encode(&self.client_id), Some(form_encode(secret)));
+            }
+            ClientCredential::SecretPost(secret) => {
+                form.push(("client_id", self.client_id.clone()));
+                form.push(("client_secret", secret.clone()));
+            }
+            ClientCredential::PrivateKeyJwt { key, key_id, algorithm } => {
+                form.push(("client_id", self.client_id.clone()));
+                form.push(("client_assertion_type", CLIENT_ASSERTION_TYPE.to_string()));
+                form.push(("client_assertion", self.client_assertion(key, key_id.as_deref(), *algorithm)?));
+            }
+        }
+
+        Ok(request.form(&form))
+    }
+
+    /// RFC 7523 client assertion signed with the client's private key.
+    fn client_assertion(&self, key: &EncodingKey, key_id: Option<&str>, algorithm: Algorithm) -> Result<String, OidcError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let mut jti = [0u8; 16];
+        SystemRandom::new().fill(&mut jti)
+            .map_err(|_| OidcError::Internal("OS RNG failed".into()))?;
+
+        let claims = ClientAssertionClaims {
+            iss: &self.client_id,
+            sub: &self.client_id,
+            aud: &self.audience,
+            jti: general_purpose::URL_SAFE_NO_PAD.encode(jti),
+            iat: now,
+            exp: now + CLIENT_ASSERTION_TTL_SECS,
+        };
+
+        let mut header = Header::new(algorithm);
+        header.kid = key_id.map(str::to_string);
+
+        encode(&header, &claims, key).map_err(|e| OidcError::Jwt(format!("client assertion: {e}")))
+    }
+}
+
+fn form_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+/// Load a private key from a ZITADEL JSON key file or a plain PEM file.
+fn load_signing_key(path: &str, algorithm: Algorithm) -> Result<(EncodingKey, Option<String>), OidcError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| OidcError::Internal(format!("failed to read client key {path}: {e}")))?;
+
+    let (pem, key_id) = match serde_json::from_str::<KeyFile>(&content) {
+        Ok(file) => (file.key, Some(file.key_id)),
+        Err(_) => (content, None),
+    };
+
+    let key = match algorithm {
+        Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(pem.as_bytes()),
+        Algorithm::EdDSA => EncodingKey::from_ed_pem(pem.as_bytes()),
+        _ => EncodingKey::from_rsa_pem(pem.as_bytes()),
+    }
+    .map_err(|e| OidcError::Internal(format!("invalid client key {path}: {e}")))?;
+
+    tracing::info!(path, "Loaded client assertion key");
+    Ok((key, key_id))
+}
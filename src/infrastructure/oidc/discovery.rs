@@ -1,8 +1,8 @@
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use crate::infrastructure::oidc::error::OidcError;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct OidcDiscovery {
     pub issuer: String,
     pub authorization_endpoint: String,
@@ -12,6 +12,18 @@ pub struct OidcDiscovery {
     pub userinfo_endpoint: Option<String>,
     #[serde(default)]
     pub end_session_endpoint: Option<String>,
+    #[serde(default)]
+    pub revocation_endpoint: Option<String>,
+    #[serde(default)]
+    pub introspection_endpoint: Option<String>,
+    #[serde(default)]
+    pub device_authorization_endpoint: Option<String>,
+    /// RFC 9126 Pushed Authorization Requests
+    #[serde(default)]
+    pub pushed_authorization_request_endpoint: Option<String>,
+    /// The provider refuses authorization requests that weren't pushed
+    #[serde(default)]
+    pub require_pushed_authorization_requests: bool,
 }
 
 impl OidcDiscovery {
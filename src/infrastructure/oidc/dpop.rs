@@ -0,0 +1,291 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use base64::{engine::general_purpose, Engine as _};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
+use moka::future::Cache;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::infrastructure::oidc::error::OidcError;
+
+/// Signature algorithms accepted for DPoP proofs (asymmetric only).
+pub const DPOP_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::ES256, Algorithm::ES384,
+    Algorithm::RS256, Algorithm::RS384, Algorithm::RS512,
+    Algorithm::PS256, Algorithm::PS384, Algorithm::PS512,
+    Algorithm::EdDSA,
+];
+
+/// Tolerated clock difference for proofs issued slightly in the future.
+const CLOCK_SKEW_SECS: u64 = 5;
+
+#[derive(Deserialize)]
+struct DpopProofClaims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: u64,
+    ath: Option<String>,
+}
+
+/// Verifies DPoP proofs (RFC 9449) sent with `Authorization: DPoP` tokens.
+/// Seen `jti`s are remembered for the proof lifetime to reject replays.
+pub struct DpopVerifier {
+    max_age_secs: u64,
+    seen: Cache<String, ()>,
+}
+
+impl DpopVerifier {
+    pub fn new(max_age_secs: u64) -> Self {
+        let seen = Cache::builder()
+            .time_to_live(Duration::from_secs(max_age_secs + CLOCK_SKEW_SECS))
+            .max_capacity(100_000)
+            .build();
+
+        Self { max_age_secs, seen }
+    }
+
+    /// Check a proof for a request (`htm`, `htu`) and the access token it accompanies,
+    /// and return the JWK SHA-256 thumbprint of the proof key (to match `cnf.jkt`).
+    pub async fn verify(&self, proof: &str, method: &str, htu: &str, access_token: &str) -> Result<String, OidcError> {
+        let invalid = |msg: &str| OidcError::InvalidDpopProof(msg.to_string());
+
+        let header_b64 = proof.split('.').next().ok_or_else(|| invalid("malformed proof"))?;
+        let header: Value = general_purpose::URL_SAFE_NO_PAD
+            .decode(header_b64)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| invalid("malformed proof header"))?;
+
+        if header.get("typ").and_then(|v| v.as_str()) != Some("dpop+jwt") {
+            return Err(invalid("typ must be dpop+jwt"));
+        }
+        let alg = header.get("alg")
+            .and_then(|v| v.as_str())
+            .and_then(|a| a.parse::<Algorithm>().ok())
+            .filter(|a| DPOP_ALGORITHMS.contains(a))
+            .ok_or_else(|| invalid("unsupported alg"))?;
+        let jwk = header.get("jwk").ok_or_else(|| invalid("missing jwk"))?;
+        if jwk.get("d").is_some() {
+            return Err(invalid("jwk must be a public key"));
+        }
+
+        let key = decoding_key(jwk, alg).ok_or_else(|| invalid("jwk does not fit alg"))?;
+        let mut validation = Validation::new(alg);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+
+        let claims = decode::<DpopProofClaims>(proof, &key, &validation)
+            .map_err(|e| OidcError::InvalidDpopProof(format!("signature: {e}")))?
+            .claims;
+
+        if claims.htm != method {
+            return Err(invalid("htm mismatch"));
+        }
+        if !same_resource(&claims.htu, htu) {
+            return Err(invalid("htu mismatch"));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if claims.iat > now + CLOCK_SKEW_SECS || now.saturating_sub(claims.iat) > self.max_age_secs {
+            return Err(invalid("iat outside the accepted window"));
+        }
+
+        let ath = general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(access_token.as_bytes()));
+        if claims.ath.as_deref() != Some(ath.as_str()) {
+            return Err(invalid("ath does not match the access token"));
+        }
+
+        let thumbprint = jwk_thumbprint(jwk).ok_or_else(|| invalid("jwk thumbprint"))?;
+
+        let entry = self.seen.entry(format!("{thumbprint}:{}", claims.jti)).or_insert(()).await;
+        if !entry.is_fresh() {
+            return Err(invalid("proof replayed"));
+        }
+
+        Ok(thumbprint)
+    }
+}
+
+/// Public key of a proof, restricted to the algorithm family of its key type.
+fn decoding_key(jwk: &Value, alg: Algorithm) -> Option<DecodingKey> {
+    let member = |name: &str| jwk.get(name).and_then(|v| v.as_str());
+
+    match (member("kty")?, member("crv"), alg) {
+        ("RSA", _, Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512) => {
+            DecodingKey::from_rsa_components(member("n")?, member("e")?).ok()
+        }
+        ("EC", Some("P-256"), Algorithm::ES256) | ("EC", Some("P-384"), Algorithm::ES384) => {
+            DecodingKey::from_ec_components(member("x")?, member("y")?).ok()
+        }
+        ("OKP", Some("Ed25519"), Algorithm::EdDSA) => DecodingKey::from_ed_components(member("x")?).ok(),
+        _ => None,
+    }
+}
+
+/// JWK SHA-256 thumbprint (RFC 7638), base64url encoded as in `cnf.jkt`.
+pub fn jwk_thumbprint(jwk: &Value) -> Option<String> {
+    let member = |name: &str| jwk.get(name).and_then(|v| v.as_str());
+
+    // Required members only, in lexicographic order, no whitespace
+    let canonical = match member("kty")? {
+        "RSA" => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, member("e")?, member("n")?),
+        "EC" => format!(r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#, member("crv")?, member("x")?, member("y")?),
+        "OKP" => format!(r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#, member("crv")?, member("x")?),
+        _ => return None,
+    };
+
+    Some(general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes())))
+}
+
+/// Compare `htu` with the request URI, ignoring query and fragment (RFC 9449 §4.3).
+fn same_resource(htu: &str, expected: &str) -> bool {
+    match (Url::parse(htu), Url::parse(expected)) {
+        (Ok(a), Ok(b)) => {
+            a.scheme() == b.scheme()
+                && a.host_str() == b.host_str()
+                && a.port_or_known_default() == b.port_or_known_default()
+                && a.path() == b.path()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+    use serde_json::json;
+
+    const HTU: &str = "https://api.example.com/api/user/me";
+    const ACCESS_TOKEN: &str = "access-token";
+
+    fn b64(bytes: &[u8]) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    /// A P-256 proof key and its public JWK.
+    fn proof_key() -> (EcdsaKeyPair, Value) {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        // Uncompressed point: 0x04 || x || y
+        let point = key.public_key().as_ref();
+        let jwk = json!({ "kty": "EC", "crv": "P-256", "x": b64(&point[1..33]), "y": b64(&point[33..]) });
+        (key, jwk)
+    }
+
+    fn sign(key: &EcdsaKeyPair, jwk: &Value, claims: Value) -> String {
+        let header = json!({ "typ": "dpop+jwt", "alg": "ES256", "jwk": jwk });
+        let input = format!("{}.{}", b64(header.to_string().as_bytes()), b64(claims.to_string().as_bytes()));
+        let signature = key.sign(&SystemRandom::new(), input.as_bytes()).unwrap();
+        format!("{input}.{}", b64(signature.as_ref()))
+    }
+
+    fn rejected_with(result: Result<String, OidcError>, reason: &str) -> bool {
+        matches!(result, Err(OidcError::InvalidDpopProof(m)) if m.starts_with(reason))
+    }
+
+    fn claims(jti: &str, htm: &str, htu: &str, token: &str) -> Value {
+        json!({
+            "jti": jti,
+            "htm": htm,
+            "htu": htu,
+            "iat": now(),
+            "ath": b64(&Sha256::digest(token.as_bytes())),
+        })
+    }
+
+    #[test]
+    fn thumbprint_matches_rfc7638_example() {
+        let jwk = json!({
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+            "e": "AQAB",
+            "alg": "RS256",
+            "kid": "2011-04-29",
+        });
+        assert_eq!(jwk_thumbprint(&jwk).as_deref(), Some("NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"));
+    }
+
+    #[tokio::test]
+    async fn accepts_a_valid_proof() {
+        let (key, jwk) = proof_key();
+        let proof = sign(&key, &jwk, claims("a", "GET", HTU, ACCESS_TOKEN));
+
+        let thumbprint = DpopVerifier::new(60).verify(&proof, "GET", HTU, ACCESS_TOKEN).await.unwrap();
+        assert_eq!(Some(thumbprint), jwk_thumbprint(&jwk));
+    }
+
+    #[tokio::test]
+    async fn htu_ignores_query_and_fragment() {
+        let (key, jwk) = proof_key();
+        let proof = sign(&key, &jwk, claims("a", "GET", &format!("{HTU}?x=1#y"), ACCESS_TOKEN));
+        assert!(DpopVerifier::new(60).verify(&proof, "GET", HTU, ACCESS_TOKEN).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_htm_mismatch() {
+        let (key, jwk) = proof_key();
+        let proof = sign(&key, &jwk, claims("a", "POST", HTU, ACCESS_TOKEN));
+        assert!(rejected_with(DpopVerifier::new(60).verify(&proof, "GET", HTU, ACCESS_TOKEN).await, "htm mismatch"));
+    }
+
+    #[tokio::test]
+    async fn rejects_htu_mismatch() {
+        let (key, jwk) = proof_key();
+        let verifier = DpopVerifier::new(60);
+        for htu in [
+            "https://api.example.com/api/user/other",
+            "http://api.example.com/api/user/me",
+            "https://evil.example.com/api/user/me",
+            "https://api.example.com:8443/api/user/me",
+        ] {
+            let proof = sign(&key, &jwk, claims(htu, "GET", htu, ACCESS_TOKEN));
+            assert!(rejected_with(verifier.verify(&proof, "GET", HTU, ACCESS_TOKEN).await, "htu mismatch"), "{htu}");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_ath_of_another_token() {
+        let (key, jwk) = proof_key();
+        let proof = sign(&key, &jwk, claims("a", "GET", HTU, "another-token"));
+        assert!(rejected_with(DpopVerifier::new(60).verify(&proof, "GET", HTU, ACCESS_TOKEN).await, "ath does not match"));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_replayed_jti() {
+        let (key, jwk) = proof_key();
+        let verifier = DpopVerifier::new(60);
+        let proof = sign(&key, &jwk, claims("once", "GET", HTU, ACCESS_TOKEN));
+
+        assert!(verifier.verify(&proof, "GET", HTU, ACCESS_TOKEN).await.is_ok());
+        assert!(rejected_with(verifier.verify(&proof, "GET", HTU, ACCESS_TOKEN).await, "proof replayed"));
+    }
+
+    #[tokio::test]
+    async fn rejects_stale_proofs() {
+        let (key, jwk) = proof_key();
+        let mut stale = claims("a", "GET", HTU, ACCESS_TOKEN);
+        stale["iat"] = json!(now() - 120);
+        let proof = sign(&key, &jwk, stale);
+        assert!(rejected_with(DpopVerifier::new(60).verify(&proof, "GET", HTU, ACCESS_TOKEN).await, "iat outside"));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_signature_by_another_key() {
+        let (key, _) = proof_key();
+        let (_, other_jwk) = proof_key();
+        let proof = sign(&key, &other_jwk, claims("a", "GET", HTU, ACCESS_TOKEN));
+        assert!(rejected_with(DpopVerifier::new(60).verify(&proof, "GET", HTU, ACCESS_TOKEN).await, "signature"));
+    }
+}
@@ -17,6 +17,9 @@ pub enum OidcError {
     #[error("invalid claim {0}: {1}")]
     InvalidClaim(&'static str, String),
 
+    #[error("provider unavailable: {0}")]
+    Unavailable(String),
+
     #[error("provider metadata error: {0}")]
     Discovery(String),
 
@@ -29,12 +32,55 @@ pub enum OidcError {
     #[error("provider error: {0}")]
     Provider(String),
 
+    #[error("unknown provider: {0}")]
+    UnknownProvider(String),
+
+    #[error("unknown downstream service: {0}")]
+    UnknownService(String),
+
     #[error("jwks key not found")]
     KeyNotFound,            // <— add
 
     #[error("not implemented: {0}")]
     NotImplemented(String), // <— add
 
+    #[error("invalid DPoP proof: {0}")]
+    InvalidDpopProof(String),
+
+    #[error("refresh token reuse detected")]
+    RefreshTokenReuse,
+
+    #[error("refresh token revoked: {0}")]
+    RefreshTokenRevoked(String),
+
     #[error("internal oidc error: {0}")]
     Internal(String),
 }
+
+impl OidcError {
+    /// Copy of an error shared by several waiters (e.g. one refresh serving parallel requests).
+    /// The kind is kept; only `reqwest` and `serde_json` sources, which can't be cloned, are
+    /// reduced to their message.
+    pub fn duplicate(&self) -> Self {
+        match self {
+            Self::Network(e) => Self::Provider(format!("network error: {e}")),
+            Self::Json(e) => Self::Provider(format!("json error: {e}")),
+            Self::Jwt(m) => Self::Jwt(m.clone()),
+            Self::MissingClaim(c) => Self::MissingClaim(c),
+            Self::InvalidClaim(c, m) => Self::InvalidClaim(c, m.clone()),
+            Self::Unavailable(m) => Self::Unavailable(m.clone()),
+            Self::Discovery(m) => Self::Discovery(m.clone()),
+            Self::Jwks(m) => Self::Jwks(m.clone()),
+            Self::TokenExchange(m) => Self::TokenExchange(m.clone()),
+            Self::Provider(m) => Self::Provider(m.clone()),
+            Self::UnknownProvider(m) => Self::UnknownProvider(m.clone()),
+            Self::UnknownService(m) => Self::UnknownService(m.clone()),
+            Self::KeyNotFound => Self::KeyNotFound,
+            Self::NotImplemented(m) => Self::NotImplemented(m.clone()),
+            Self::InvalidDpopProof(m) => Self::InvalidDpopProof(m.clone()),
+            Self::RefreshTokenReuse => Self::RefreshTokenReuse,
+            Self::RefreshTokenRevoked(m) => Self::RefreshTokenRevoked(m.clone()),
+            Self::Internal(m) => Self::Internal(m.clone()),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use moka::Expiry;
+use moka::future::Cache;
+use reqwest::Client;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::infrastructure::oidc::claims::OidcClaims;
+use crate::infrastructure::oidc::client_auth::ClientAuth;
+use crate::infrastructure::oidc::error::OidcError;
+use crate::infrastructure::oidc::validation::ValidationPolicy;
+
+/// Expire cache entries when the token they describe expires.
+pub struct UntilTokenExpiry;
+
+impl Expiry<String, OidcClaims> for UntilTokenExpiry {
+    fn expire_after_create(&self, _key: &String, value: &OidcClaims, _created_at: Instant) -> Option<Duration> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Some(Duration::from_secs(value.exp.saturating_sub(now)))
+    }
+}
+
+/// Cache of successful introspection results, keyed by a SHA-256 of the token
+/// so raw tokens are never kept in memory.
+pub fn build_introspection_cache() -> Cache<String, OidcClaims> {
+    Cache::builder()
+        .max_capacity(10_000)
+        .expire_after(UntilTokenExpiry)
+        .build()
+}
+
+pub fn token_cache_key(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Call an RFC 7662 introspection endpoint and return the raw response of an *active* token.
+pub async fn introspect(
+    http_client: &Client,
+    client_auth: &ClientAuth,
+    endpoint: &str,
+    token: &str,
+) -> Result<Value, OidcError> {
+    let form = vec![
+        ("token", token.to_string()),
+        ("token_type_hint", "access_token".to_string()),
+    ];
+
+    let body = client_auth
+        .form_post(http_client, endpoint, form)?
+        .send()
+        .await
+        .map_err(OidcError::Network)?
+        .error_for_status()
+        .map_err(OidcError::Network)?
+        .json::<Value>()
+        .await
+        .map_err(OidcError::Network)?;
+
+    if !body.get("active").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return Err(OidcError::Jwt("token is not active".into()));
+    }
+    Ok(body)
+}
+
+/// Check the issuer of an introspection response and apply the same policy as JWT
+/// validation, then map it into `OidcClaims`.
+pub fn claims_from_introspection(
+    mut body: Value,
+    issuer: &str,
+    policy: &ValidationPolicy,
+) -> Result<OidcClaims, OidcError> {
+    match body.get("iss").and_then(|v| v.as_str()) {
+        Some(iss) if iss.trim_end_matches('/') != issuer.trim_end_matches('/') => {
+            return Err(OidcError::InvalidClaim("iss", iss.to_string()));
+        }
+        Some(_) => {}
+        // `iss` is optional in RFC 7662; the response comes from our issuer
+        None => body["iss"] = Value::String(issuer.to_string()),
+    }
+
+    policy.check_claims(&body)?;
+    OidcClaims::from_value(&body)
+}
@@ -1,12 +1,20 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::{Duration, Instant};
+use arc_swap::ArcSwap;
+use base64::{engine::general_purpose, Engine as _};
 use crate::infrastructure::oidc::error::OidcError;
 use crate::infrastructure::oidc::claims::OidcClaims;
 use crate::infrastructure::oidc::discovery::OidcDiscovery;
-use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use crate::infrastructure::oidc::validation::ValidationPolicy;
+use jsonwebtoken::{Algorithm, DecodingKey, decode, decode_header};
 use reqwest::Client;
-use serde::Deserialize;
+use reqwest::header::CACHE_CONTROL;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Jwk {
     pub kid: String,
     pub kty: String,
@@ -26,70 +34,497 @@ pub struct Jwk {
     pub use_: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct JwksResponse {
     pub keys: Vec<Jwk>,
 }
 
+/// Timing knobs for provider metadata refresh and JWKS rotation handling.
+#[derive(Debug, Clone)]
+pub struct JwksSettings {
+    /// Time between discovery document refreshes while the issuer is reachable.
+    pub discovery_refresh_interval: Duration,
+    /// Upper bound between scheduled refreshes (a shorter `Cache-Control: max-age` wins).
+    pub refresh_interval: Duration,
+    /// Minimum time between two fetches, also used as the retry delay after a failure.
+    pub min_refetch_interval: Duration,
+    /// How long keys that disappeared from the JWKS are still accepted.
+    pub key_grace_period: Duration,
+    /// How long a persisted discovery document and JWKS may be used while the issuer is down.
+    pub snapshot_max_age: Duration,
+}
+
+impl Default for JwksSettings {
+    fn default() -> Self {
+        Self {
+            discovery_refresh_interval: Duration::from_secs(60 * 60),
+            refresh_interval: Duration::from_secs(60 * 60),
+            min_refetch_interval: Duration::from_secs(30),
+            key_grace_period: Duration::from_secs(60 * 60),
+            snapshot_max_age: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// A decoding key together with the signature algorithms it may be used with.
 #[derive(Clone)]
+struct VerifyingKey {
+    key: DecodingKey,
+    algorithms: Vec<Algorithm>,
+}
+
+impl VerifyingKey {
+    /// Build a key from a JWK. Returns `None` for encryption keys and unsupported key types.
+    fn from_jwk(jwk: &Jwk) -> Option<Self> {
+        if jwk.use_.as_deref().is_some_and(|u| u != "sig") {
+            return None;
+        }
+
+        let (key, family): (DecodingKey, &[Algorithm]) = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+            ("RSA", _) => (
+                DecodingKey::from_rsa_components(jwk.n.as_deref()?, jwk.e.as_deref()?).ok()?,
+                &[Algorithm::RS256, Algorithm::RS384, Algorithm::RS512, Algorithm::PS256, Algorithm::PS384, Algorithm::PS512],
+            ),
+            ("EC", Some("P-256")) => (
+                DecodingKey::from_ec_components(jwk.x.as_deref()?, jwk.y.as_deref()?).ok()?,
+                &[Algorithm::ES256],
+            ),
+            ("EC", Some("P-384")) => (
+                DecodingKey::from_ec_components(jwk.x.as_deref()?, jwk.y.as_deref()?).ok()?,
+                &[Algorithm::ES384],
+            ),
+            ("OKP", Some("Ed25519")) => (
+                DecodingKey::from_ed_components(jwk.x.as_deref()?).ok()?,
+                &[Algorithm::EdDSA],
+            ),
+            _ => {
+                tracing::debug!(kid = %jwk.kid, kty = %jwk.kty, crv = ?jwk.crv, "Skipping unsupported JWK");
+                return None;
+            }
+        };
+
+        // A published `alg` pins the key to that single algorithm, as long as it fits the key type
+        let algorithms = match jwk.alg.as_deref() {
+            Some(alg) => {
+                let alg = alg.parse::<Algorithm>().ok().filter(|a| family.contains(a))?;
+                vec![alg]
+            }
+            None => family.to_vec(),
+        };
+
+        Some(Self { key, algorithms })
+    }
+}
+
+#[derive(Clone)]
+struct CachedKey {
+    key: VerifyingKey,
+    /// Set once the key is no longer published by the provider.
+    retired_at: Option<Instant>,
+}
+
+/// JWKS cache with scheduled refresh and rate-limited refetch on unknown `kid`.
+///
+/// Readers only load an `Arc` snapshot of the current key set; the mutex is
+/// taken exclusively by the refresh path.
 pub struct JwkCache {
-    keys: HashMap<String, DecodingKey>,
+    http_client: Client,
+    jwks_uri: String,
+    settings: JwksSettings,
+    current: ArcSwap<HashMap<String, CachedKey>>,
+    /// Key set as last fetched (or restored), for persisting
+    published: ArcSwap<JwksResponse>,
+    /// Called after every successful fetch
+    on_fetch: OnceLock<Box<dyn Fn(Arc<JwksResponse>) + Send + Sync>>,
+    next_refresh_secs: AtomicU64,
+    /// Guards fetching and records the time of the last fetch attempt.
+    last_fetch: Mutex<Option<Instant>>,
 }
 
 impl JwkCache {
-    pub async fn new(http_client: &Client, jwks_uri: &str) -> Result<Self, OidcError> {
-        let resp = http_client.get(jwks_uri).send().await.map_err(OidcError::Network)?;
-        let body = resp.error_for_status().map_err(OidcError::Network)?
-            .json::<JwksResponse>().await.map_err(OidcError::Network)?;
-
-        let mut map = HashMap::new();
-        for k in body.keys {
-            if let (Some(n), Some(e)) = (k.n.clone(), k.e.clone()) {
-                if let Ok(dec) = DecodingKey::from_rsa_components(&n, &e) {
-                    map.insert(k.kid.clone(), dec);
+    pub async fn new(http_client: &Client, jwks_uri: &str, settings: JwksSettings) -> Result<Self, OidcError> {
+        let (published, max_age) = fetch_keys(http_client, jwks_uri).await?;
+        let next_refresh = next_refresh_in(&settings, max_age);
+        Ok(Self::with_keys(http_client, jwks_uri, settings, published, next_refresh, Some(Instant::now())))
+    }
+
+    /// Start from a previously persisted key set; the first refresh is attempted soon.
+    pub fn restore(http_client: &Client, jwks_uri: &str, settings: JwksSettings, published: JwksResponse) -> Self {
+        let next_refresh = settings.min_refetch_interval;
+        Self::with_keys(http_client, jwks_uri, settings, published, next_refresh, None)
+    }
+
+    fn with_keys(
+        http_client: &Client,
+        jwks_uri: &str,
+        settings: JwksSettings,
+        published: JwksResponse,
+        next_refresh: Duration,
+        last_fetch: Option<Instant>,
+    ) -> Self {
+        Self {
+            http_client: http_client.clone(),
+            jwks_uri: jwks_uri.to_string(),
+            current: ArcSwap::from_pointee(verifying_keys(&published)
+                .into_iter()
+                .map(|(kid, key)| (kid, CachedKey { key, retired_at: None }))
+                .collect()),
+            published: ArcSwap::from_pointee(published),
+            on_fetch: OnceLock::new(),
+            next_refresh_secs: AtomicU64::new(next_refresh.as_secs()),
+            settings,
+            last_fetch: Mutex::new(last_fetch),
+        }
+    }
+
+    pub fn jwks_uri(&self) -> &str {
+        &self.jwks_uri
+    }
+
+    /// The key set as published by the provider at the last fetch.
+    pub fn published(&self) -> Arc<JwksResponse> {
+        self.published.load_full()
+    }
+
+    /// Register a callback for every later successful fetch (at most one).
+    pub fn on_fetch(&self, callback: impl Fn(Arc<JwksResponse>) + Send + Sync + 'static) {
+        if self.on_fetch.set(Box::new(callback)).is_err() {
+            tracing::warn!(jwks_uri = %self.jwks_uri, "JWKS fetch callback already registered");
+        }
+    }
+
+    /// Start the background refresh loop. It stops once the cache is dropped.
+    pub fn spawn_refresh_task(self: &Arc<Self>) {
+        let weak: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let delay = match weak.upgrade() {
+                    Some(cache) => Duration::from_secs(cache.next_refresh_secs.load(Ordering::Relaxed)),
+                    None => return,
+                };
+                tokio::time::sleep(delay).await;
+
+                let Some(cache) = weak.upgrade() else { return };
+                if let Err(e) = cache.refresh(true).await {
+                    tracing::warn!(jwks_uri = %cache.jwks_uri, error = %e, "Scheduled JWKS refresh failed");
                 }
             }
+        });
+    }
+
+    /// Fetch the JWKS and swap in the new key set, keeping retired keys for the grace period.
+    /// Unless `force` is set, the fetch is skipped when the last one is too recent.
+    async fn refresh(&self, force: bool) -> Result<(), OidcError> {
+        let mut last_fetch = self.last_fetch.lock().await;
+        if !force && last_fetch.is_some_and(|t| t.elapsed() < self.settings.min_refetch_interval) {
+            tracing::debug!("JWKS refetch suppressed by rate limit");
+            return Ok(());
+        }
+        *last_fetch = Some(Instant::now());
+
+        let (published, max_age) = match fetch_keys(&self.http_client, &self.jwks_uri).await {
+            Ok(v) => v,
+            Err(e) => {
+                // Keep the current keys and retry sooner than the regular schedule
+                self.set_next_refresh(self.settings.min_refetch_interval);
+                return Err(e);
+            }
+        };
+
+        let now = Instant::now();
+        let previous = self.current.load();
+        let mut keys: HashMap<String, CachedKey> = verifying_keys(&published).into_iter()
+            .map(|(kid, key)| (kid, CachedKey { key, retired_at: None }))
+            .collect();
+
+        for (kid, old) in previous.iter() {
+            if keys.contains_key(kid) {
+                continue;
+            }
+            let retired_at = old.retired_at.unwrap_or(now);
+            if now.duration_since(retired_at) < self.settings.key_grace_period {
+                keys.insert(kid.clone(), CachedKey { retired_at: Some(retired_at), ..old.clone() });
+            }
         }
-        Ok(Self { keys: map })
+
+        let added = keys.keys().filter(|kid| !previous.contains_key(*kid)).count();
+        if added > 0 {
+            tracing::info!(jwks_uri = %self.jwks_uri, added, total = keys.len(), "JWKS rotated");
+        }
+
+        self.current.store(Arc::new(keys));
+        let published = Arc::new(published);
+        self.published.store(published.clone());
+        self.set_next_refresh(next_refresh_in(&self.settings, max_age));
+        if let Some(callback) = self.on_fetch.get() {
+            callback(published);
+        }
+        Ok(())
+    }
+
+    fn set_next_refresh(&self, delay: Duration) {
+        self.next_refresh_secs.store(delay.as_secs(), Ordering::Relaxed);
+    }
+
+    fn lookup(&self, kid: &str) -> Option<VerifyingKey> {
+        let keys = self.current.load();
+        let cached = keys.get(kid)?;
+        match cached.retired_at {
+            Some(t) if t.elapsed() >= self.settings.key_grace_period => None,
+            _ => Some(cached.key.clone()),
+        }
+    }
+
+    async fn key_for(&self, kid: &str) -> Result<VerifyingKey, OidcError> {
+        if let Some(key) = self.lookup(kid) {
+            return Ok(key);
+        }
+
+        // Unknown kid: the provider may have rotated its keys
+        tracing::info!(kid, "kid not in cached JWKS, refetching");
+        self.refresh(false).await?;
+
+        self.lookup(kid).ok_or_else(|| OidcError::Jwt("kid not found in JWKS".into()))
+    }
+
+    pub async fn validate(&self, token: &str, discovery: &OidcDiscovery, policy: &ValidationPolicy) -> Result<OidcClaims, OidcError> {
+        let raw = self.verify(token, discovery, policy, &["exp"]).await?;
+        // Roles are provider specific and filled in by the provider's RoleMapper
+        OidcClaims::from_value(&raw)
     }
 
-    pub fn validate(&self, token: &str, discovery: &OidcDiscovery, expected_aud: Option<&str>) -> Result<OidcClaims, OidcError> {
+    /// Verify signature and issuer, apply `policy` and return the raw payload.
+    /// `required_claims` must be present in the token, e.g. `["exp"]` for access and ID tokens.
+    pub async fn verify(
+        &self,
+        token: &str,
+        discovery: &OidcDiscovery,
+        policy: &ValidationPolicy,
+        required_claims: &[&str],
+    ) -> Result<serde_json::Value, OidcError> {
         let header = decode_header(token).map_err(|e| OidcError::Jwt(e.to_string()))?;
         let kid = header.kid.ok_or_else(|| OidcError::Jwt("missing kid".into()))?;
-        let key = self.keys.get(&kid).ok_or_else(|| OidcError::Jwt("kid not found in JWKS".into()))?;
+        let key = self.key_for(&kid).await?;
 
-        let mut validation = Validation::new(Algorithm::RS256);
-        validation.set_issuer(&[discovery.issuer.as_str()]);
-        if let Some(aud) = expected_aud {
-            validation.set_audience(&[aud]);
+        // Reject algorithm confusion: the header alg must match the key type (and its JWK alg, if any)
+        if !key.algorithms.contains(&header.alg) {
+            return Err(OidcError::Jwt(format!("algorithm {:?} not allowed for key {}", header.alg, kid)));
         }
 
-        let data = decode::<serde_json::Value>(token, key, &validation)
+        let validation = policy.jwt_validation(header.alg, &discovery.issuer, required_claims);
+        let data = decode::<serde_json::Value>(token, &key.key, &validation)
             .map_err(|e| OidcError::Jwt(e.to_string()))?;
-        let c = data.claims;
-
-        let exp = c.get("exp").and_then(|v| v.as_u64()).ok_or(OidcError::MissingClaim("exp"))?;
-        let iat = c.get("iat").and_then(|v| v.as_u64()).unwrap_or(0);
-        let iss = c.get("iss").and_then(|v| v.as_str()).ok_or(OidcError::MissingClaim("iss"))?.to_string();
-        let aud = match c.get("aud") {
-            Some(serde_json::Value::String(s)) => s.clone(),
-            Some(serde_json::Value::Array(arr)) => arr.get(0).and_then(|v| v.as_str()).unwrap_or_default().to_string(),
-            _ => "".to_string()
-        };
-        let sub = c.get("sub").and_then(|v| v.as_str()).ok_or(OidcError::MissingClaim("sub"))?.to_string();
-        let email = c.get("email").and_then(|v| v.as_str()).map(|s| s.to_string());
-        let preferred_username = c.get("preferred_username").and_then(|v| v.as_str()).map(|s| s.to_string());
-
-        // Zitadel roles: { "urn:zitadel:iam:org:project:roles": { "roleA": true, ... } }
-        let mut roles: Vec<String> = Vec::new();
-        if let Some(obj) = c.get("urn:zitadel:iam:org:project:roles").and_then(|v| v.as_object()) {
-            for (k, v) in obj {
-                if v.as_bool().unwrap_or(false) {
-                    roles.push(k.clone());
-                }
-            }
+
+        policy.check_claims(&data.claims)?;
+        Ok(data.claims)
+    }
+}
+
+/// Whether `token` has the shape of a compact JWS (`header.payload.signature`).
+pub fn looks_like_jwt(token: &str) -> bool {
+    let mut parts = token.split('.');
+    let header = parts.next().unwrap_or_default();
+    parts.count() == 2 && general_purpose::URL_SAFE_NO_PAD.decode(header).is_ok()
+}
+
+/// Decode a JWT payload *without* verifying it. Only use on tokens that are
+/// validated separately, or to pick which validator to use.
+pub fn decode_jwt_payload(token: &str) -> Result<serde_json::Value, OidcError> {
+    let payload_b64 = token.split('.').nth(1).ok_or_else(|| OidcError::Jwt("malformed JWT".into()))?;
+    let bytes = general_purpose::URL_SAFE_NO_PAD
+        .decode(payload_b64)
+        .map_err(|e| OidcError::Jwt(format!("payload b64 decode: {e}")))?;
+    serde_json::from_slice::<serde_json::Value>(&bytes).map_err(OidcError::Json)
+}
+
+async fn fetch_keys(http_client: &Client, jwks_uri: &str) -> Result<(JwksResponse, Option<Duration>), OidcError> {
+    let resp = http_client.get(jwks_uri).send().await.map_err(OidcError::Network)?
+        .error_for_status().map_err(OidcError::Network)?;
+    let max_age = resp.headers()
+        .get(CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_max_age);
+    let body = resp.json::<JwksResponse>().await.map_err(OidcError::Network)?;
+    Ok((body, max_age))
+}
+
+fn verifying_keys(jwks: &JwksResponse) -> HashMap<String, VerifyingKey> {
+    jwks.keys
+        .iter()
+        .filter_map(|k| VerifyingKey::from_jwk(k).map(|key| (k.kid.clone(), key)))
+        .collect()
+}
+
+fn parse_max_age(cache_control: &str) -> Option<Duration> {
+    cache_control
+        .split(',')
+        .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+        .find_map(|secs| secs.trim_matches('"').parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+fn next_refresh_in(settings: &JwksSettings, max_age: Option<Duration>) -> Duration {
+    max_age
+        .map(|age| age.min(settings.refresh_interval))
+        .unwrap_or(settings.refresh_interval)
+        .max(settings.min_refetch_interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING};
+    use serde_json::{json, Value};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const ISSUER: &str = "https://idp.example.com";
+
+    fn b64(bytes: &[u8]) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    fn jwk(value: Value) -> Jwk {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn rsa_jwk(alg: Option<&str>) -> Jwk {
+        // Modulus and exponent are only decoded, never used to verify in these tests
+        jwk(json!({ "kid": "rsa", "kty": "RSA", "n": b64(&[0xc5; 256]), "e": "AQAB", "alg": alg }))
+    }
+
+    /// An EC key pair and its public JWK. The public point is `0x04 || x || y`.
+    fn ec_key(alg: &'static ring::signature::EcdsaSigningAlgorithm, crv: &str, kid: &str) -> (EcdsaKeyPair, Jwk) {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(alg, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(alg, pkcs8.as_ref(), &rng).unwrap();
+        let point = key.public_key().as_ref();
+        let half = (point.len() - 1) / 2;
+        let jwk = jwk(json!({ "kid": kid, "kty": "EC", "crv": crv, "x": b64(&point[1..=half]), "y": b64(&point[half + 1..]) }));
+        (key, jwk)
+    }
+
+    fn ed25519_key() -> (Ed25519KeyPair, Jwk) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let jwk = jwk(json!({ "kid": "ed", "kty": "OKP", "crv": "Ed25519", "x": b64(key.public_key().as_ref()) }));
+        (key, jwk)
+    }
+
+    fn cache(keys: Vec<Jwk>) -> JwkCache {
+        let published = JwksResponse { keys };
+        JwkCache::with_keys(&Client::new(), "https://idp.example.com/jwks", JwksSettings::default(), published, Duration::from_secs(3600), Some(Instant::now()))
+    }
+
+    fn discovery() -> OidcDiscovery {
+        serde_json::from_value(json!({
+            "issuer": ISSUER,
+            "authorization_endpoint": "https://idp.example.com/authorize",
+            "token_endpoint": "https://idp.example.com/token",
+            "jwks_uri": "https://idp.example.com/jwks",
+        }))
+        .unwrap()
+    }
+
+    fn policy() -> ValidationPolicy {
+        ValidationPolicy { audiences: vec!["api".into()], ..ValidationPolicy::default() }
+    }
+
+    fn claims() -> Value {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        json!({ "iss": ISSUER, "sub": "u1", "aud": "api", "iat": now, "exp": now + 300 })
+    }
+
+    /// A compact JWS with the given header; `sign` produces the signature over the signing input.
+    fn token(header: Value, sign: impl FnOnce(&[u8]) -> Vec<u8>) -> String {
+        let input = format!("{}.{}", b64(header.to_string().as_bytes()), b64(claims().to_string().as_bytes()));
+        let signature = sign(input.as_bytes());
+        format!("{input}.{}", b64(&signature))
+    }
+
+    fn unsigned(alg: &str, kid: &str) -> String {
+        token(json!({ "alg": alg, "kid": kid }), |_| b"not a signature".to_vec())
+    }
+
+    fn disallowed(result: Result<Value, OidcError>) -> bool {
+        matches!(result, Err(OidcError::Jwt(m)) if m.starts_with("algorithm"))
+    }
+
+    #[test]
+    fn builds_keys_with_the_algorithms_of_their_type() {
+        let (_, p256) = ec_key(&ECDSA_P256_SHA256_FIXED_SIGNING, "P-256", "p256");
+        let (_, p384) = ec_key(&ECDSA_P384_SHA384_FIXED_SIGNING, "P-384", "p384");
+        let (_, ed) = ed25519_key();
+
+        assert_eq!(VerifyingKey::from_jwk(&p256).unwrap().algorithms, vec![Algorithm::ES256]);
+        assert_eq!(VerifyingKey::from_jwk(&p384).unwrap().algorithms, vec![Algorithm::ES384]);
+        assert_eq!(VerifyingKey::from_jwk(&ed).unwrap().algorithms, vec![Algorithm::EdDSA]);
+        assert!(!VerifyingKey::from_jwk(&rsa_jwk(None)).unwrap().algorithms.contains(&Algorithm::HS256));
+    }
+
+    #[test]
+    fn published_alg_pins_the_key() {
+        assert_eq!(VerifyingKey::from_jwk(&rsa_jwk(Some("PS256"))).unwrap().algorithms, vec![Algorithm::PS256]);
+    }
+
+    #[test]
+    fn rejects_alg_conflicting_with_kty() {
+        assert!(VerifyingKey::from_jwk(&rsa_jwk(Some("ES256"))).is_none());
+        assert!(VerifyingKey::from_jwk(&rsa_jwk(Some("HS256"))).is_none());
+
+        let (_, mut p256) = ec_key(&ECDSA_P256_SHA256_FIXED_SIGNING, "P-256", "p256");
+        p256.alg = Some("ES384".into());
+        assert!(VerifyingKey::from_jwk(&p256).is_none());
+    }
+
+    #[test]
+    fn skips_encryption_and_unknown_keys() {
+        let mut enc = rsa_jwk(None);
+        enc.use_ = Some("enc".into());
+        assert!(VerifyingKey::from_jwk(&enc).is_none());
+        assert!(VerifyingKey::from_jwk(&jwk(json!({ "kid": "oct", "kty": "oct" }))).is_none());
+    }
+
+    #[tokio::test]
+    async fn verifies_ec_and_ed25519_signatures() {
+        let (p256, p256_jwk) = ec_key(&ECDSA_P256_SHA256_FIXED_SIGNING, "P-256", "p256");
+        let (p384, p384_jwk) = ec_key(&ECDSA_P384_SHA384_FIXED_SIGNING, "P-384", "p384");
+        let (ed, ed_jwk) = ed25519_key();
+        let cache = cache(vec![p256_jwk, p384_jwk, ed_jwk]);
+        let rng = SystemRandom::new();
+
+        let tokens = [
+            token(json!({ "alg": "ES256", "kid": "p256" }), |m| p256.sign(&rng, m).unwrap().as_ref().to_vec()),
+            token(json!({ "alg": "ES384", "kid": "p384" }), |m| p384.sign(&rng, m).unwrap().as_ref().to_vec()),
+            token(json!({ "alg": "EdDSA", "kid": "ed" }), |m| ed.sign(m).as_ref().to_vec()),
+        ];
+        for token in tokens {
+            let claims = cache.verify(&token, &discovery(), &policy(), &["exp"]).await.unwrap();
+            assert_eq!(claims["sub"], "u1");
         }
+    }
+
+    #[tokio::test]
+    async fn rejects_header_alg_the_key_does_not_allow() {
+        let (_, p256_jwk) = ec_key(&ECDSA_P256_SHA256_FIXED_SIGNING, "P-256", "p256");
+        let cache = cache(vec![rsa_jwk(None), p256_jwk]);
+
+        // HMAC with the RSA public key as secret, and RSA against an EC key
+        assert!(disallowed(cache.verify(&unsigned("HS256", "rsa"), &discovery(), &policy(), &["exp"]).await));
+        assert!(disallowed(cache.verify(&unsigned("RS256", "p256"), &discovery(), &policy(), &["exp"]).await));
+        assert!(disallowed(cache.verify(&unsigned("ES384", "p256"), &discovery(), &policy(), &["exp"]).await));
+        // `none` isn't a signature algorithm at all
+        assert!(cache.verify(&unsigned("none", "rsa"), &discovery(), &policy(), &["exp"]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_a_signature_by_another_key() {
+        let (other, _) = ec_key(&ECDSA_P256_SHA256_FIXED_SIGNING, "P-256", "p256");
+        let (_, p256_jwk) = ec_key(&ECDSA_P256_SHA256_FIXED_SIGNING, "P-256", "p256");
+        let cache = cache(vec![p256_jwk]);
+        let rng = SystemRandom::new();
 
-        Ok(OidcClaims { exp, iat, iss, aud, sub, email, preferred_username, roles })
+        let forged = token(json!({ "alg": "ES256", "kid": "p256" }), |m| other.sign(&rng, m).unwrap().as_ref().to_vec());
+        assert!(cache.verify(&forged, &discovery(), &policy(), &["exp"]).await.is_err());
     }
 }
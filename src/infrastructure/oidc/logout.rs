@@ -0,0 +1,96 @@
+use serde_json::Value;
+use crate::infrastructure::oidc::error::OidcError;
+
+/// `events` member identifying a back-channel logout token.
+pub const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+
+/// Claims of a validated back-channel logout token. At least one of `sub` and `sid` is set.
+#[derive(Debug, Clone)]
+pub struct LogoutToken {
+    pub iss: String,
+    pub sub: Option<String>,
+    pub sid: Option<String>,
+    /// Unique token identifier, recorded to reject replays
+    pub jti: String,
+    pub iat: u64,
+    pub exp: Option<u64>,
+}
+
+impl LogoutToken {
+    /// Check the logout-specific claims of an already verified token (spec §2.6, steps 4-6).
+    pub fn from_value(c: &Value) -> Result<Self, OidcError> {
+        let is_logout_event = c.get("events")
+            .and_then(|events| events.get(BACKCHANNEL_LOGOUT_EVENT))
+            .is_some_and(Value::is_object);
+        if !is_logout_event {
+            return Err(OidcError::InvalidClaim("events", "not a back-channel logout token".into()));
+        }
+
+        // A nonce would mean an ID token is being replayed as a logout token
+        if c.get("nonce").is_some() {
+            return Err(OidcError::InvalidClaim("nonce", "must not be present".into()));
+        }
+
+        let iss = c.get("iss").and_then(|v| v.as_str()).ok_or(OidcError::MissingClaim("iss"))?.to_string();
+        let iat = c.get("iat").and_then(|v| v.as_u64()).ok_or(OidcError::MissingClaim("iat"))?;
+        let exp = c.get("exp").and_then(|v| v.as_u64());
+        let jti = c.get("jti").and_then(|v| v.as_str()).ok_or(OidcError::MissingClaim("jti"))?.to_string();
+        let sub = c.get("sub").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let sid = c.get("sid").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        if sub.is_none() && sid.is_none() {
+            return Err(OidcError::MissingClaim("sub or sid"));
+        }
+
+        Ok(Self { iss, sub, sid, jti, iat, exp })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claims() -> Value {
+        json!({
+            "iss": "https://idp.example.com",
+            "sub": "user-1",
+            "sid": "session-1",
+            "jti": "logout-1",
+            "iat": 1_700_000_000u64,
+            "exp": 1_700_000_120u64,
+            "events": { BACKCHANNEL_LOGOUT_EVENT: {} },
+        })
+    }
+
+    #[test]
+    fn reads_logout_claims() {
+        let token = LogoutToken::from_value(&claims()).unwrap();
+        assert_eq!(token.jti, "logout-1");
+        assert_eq!(token.sid.as_deref(), Some("session-1"));
+        assert_eq!(token.exp, Some(1_700_000_120));
+    }
+
+    #[test]
+    fn requires_jti() {
+        let mut c = claims();
+        c.as_object_mut().unwrap().remove("jti");
+        assert!(matches!(LogoutToken::from_value(&c), Err(OidcError::MissingClaim("jti"))));
+    }
+
+    #[test]
+    fn requires_sub_or_sid() {
+        let mut c = claims();
+        c.as_object_mut().unwrap().remove("sub");
+        assert!(LogoutToken::from_value(&c).is_ok());
+        c.as_object_mut().unwrap().remove("sid");
+        assert!(matches!(LogoutToken::from_value(&c), Err(OidcError::MissingClaim("sub or sid"))));
+    }
+
+    #[test]
+    fn rejects_id_token_with_nonce() {
+        let mut c = claims();
+        c["nonce"] = json!("n");
+        assert!(matches!(LogoutToken::from_value(&c), Err(OidcError::InvalidClaim("nonce", _))));
+    }
+}
@@ -0,0 +1,364 @@
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::Duration;
+use arc_swap::ArcSwapOption;
+use reqwest::Client;
+use serde::Serialize;
+use time::OffsetDateTime;
+use crate::domain::entities::OidcMetadataSnapshot;
+use crate::domain::repositories::OidcMetadataRepository;
+use crate::infrastructure::oidc::discovery::OidcDiscovery;
+use crate::infrastructure::oidc::error::OidcError;
+use crate::infrastructure::oidc::jwk::{JwkCache, JwksResponse, JwksSettings};
+
+/// First retry delay while the issuer is unreachable; doubles up to `MAX_RETRY_DELAY`.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// A discovery document together with the key set it points to.
+pub struct LoadedMetadata {
+    pub discovery: OidcDiscovery,
+    pub jwks: Arc<JwkCache>,
+    /// Set when restored from a persisted snapshot: not trusted past this point
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+/// Provider reachability as seen by the metadata refresh loop.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProviderHealth {
+    /// Discovery document and keys are loaded, possibly from an earlier refresh or a snapshot
+    pub available: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_refresh: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_attempt: Option<OffsetDateTime>,
+    /// Error of the latest attempt; cleared by the next successful refresh
+    pub last_error: Option<String>,
+    /// Expiry of the persisted snapshot in use, while running on one
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub snapshot_expires_at: Option<OffsetDateTime>,
+}
+
+/// Discovery document and JWKS of one provider, refreshed in the background.
+///
+/// Construction never fails on an unreachable issuer: the provider starts from the last
+/// persisted snapshot if one is still valid, otherwise without metadata (`OidcError::Unavailable`),
+/// and the refresh loop retries with backoff. Every successful live discovery load and every
+/// key set fetched for it is persisted.
+pub struct ProviderMetadata {
+    this: Weak<Self>,
+    http_client: Client,
+    issuer_url: String,
+    /// Issuer identifier from the first discovery document; later documents must match it
+    issuer: OnceLock<String>,
+    settings: JwksSettings,
+    store: Arc<dyn OidcMetadataRepository>,
+    current: ArcSwapOption<LoadedMetadata>,
+    health: Mutex<ProviderHealth>,
+}
+
+impl ProviderMetadata {
+    /// Try a first load, fall back to the persisted snapshot, and start the refresh loop.
+    pub async fn load(
+        http_client: Client,
+        issuer_url: &str,
+        settings: JwksSettings,
+        store: Arc<dyn OidcMetadataRepository>,
+    ) -> Arc<Self> {
+        let metadata = Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            http_client,
+            issuer_url: issuer_url.to_string(),
+            issuer: OnceLock::new(),
+            settings,
+            store,
+            current: ArcSwapOption::empty(),
+            health: Mutex::new(ProviderHealth::default()),
+        });
+
+        if let Err(e) = metadata.refresh().await {
+            match metadata.restore().await {
+                Ok(Some(expires_at)) => {
+                    tracing::warn!(issuer = %issuer_url, error = %e, %expires_at, "OIDC issuer unreachable, using persisted metadata");
+                }
+                Ok(None) => {
+                    tracing::warn!(issuer = %issuer_url, error = %e, "OIDC issuer unreachable and no valid persisted metadata, starting degraded");
+                }
+                Err(restore_error) => {
+                    tracing::warn!(issuer = %issuer_url, error = %e, %restore_error, "OIDC issuer unreachable and persisted metadata unreadable, starting degraded");
+                }
+            }
+        }
+        metadata.spawn_refresh_task();
+        metadata
+    }
+
+    /// The issuer identifier (`iss`), or the configured issuer URL before discovery succeeded.
+    pub fn issuer(&self) -> &str {
+        self.issuer.get().map(String::as_str).unwrap_or_else(|| {
+            self.issuer_url
+                .strip_suffix("/.well-known/openid-configuration")
+                .unwrap_or(&self.issuer_url)
+        })
+    }
+
+    pub fn current(&self) -> Result<Arc<LoadedMetadata>, OidcError> {
+        let loaded = self.current.load_full()
+            .ok_or_else(|| OidcError::Unavailable(format!("no metadata for {} yet", self.issuer_url)))?;
+        if loaded.expires_at.is_some_and(|t| t <= OffsetDateTime::now_utc()) {
+            return Err(OidcError::Unavailable(format!("persisted metadata for {} expired", self.issuer_url)));
+        }
+        Ok(loaded)
+    }
+
+    /// Like `current`, but rejects a persisted snapshot: for flows that need the issuer itself.
+    pub fn live(&self) -> Result<Arc<LoadedMetadata>, OidcError> {
+        let loaded = self.current()?;
+        if loaded.expires_at.is_some() {
+            return Err(OidcError::Unavailable(format!("{} unreachable, running on persisted metadata", self.issuer_url)));
+        }
+        Ok(loaded)
+    }
+
+    pub fn health(&self) -> ProviderHealth {
+        let mut health = self.health.lock().expect("health lock poisoned").clone();
+        health.available = self.current().is_ok();
+        health.snapshot_expires_at = self.current.load().as_ref().and_then(|m| m.expires_at);
+        health
+    }
+
+    /// Fetch discovery (and the JWKS if `jwks_uri` changed) and record the outcome.
+    pub async fn refresh(&self) -> Result<(), OidcError> {
+        let result = self.fetch().await;
+
+        let now = OffsetDateTime::now_utc();
+        let mut health = self.health.lock().expect("health lock poisoned");
+        health.last_attempt = Some(now);
+        match &result {
+            Ok(()) => {
+                health.last_refresh = Some(now);
+                health.last_error = None;
+            }
+            Err(e) => health.last_error = Some(e.to_string()),
+        }
+        result
+    }
+
+    async fn fetch(&self) -> Result<(), OidcError> {
+        let discovery = OidcDiscovery::fetch(&self.http_client, &self.issuer_url).await?;
+        if let Some(issuer) = self.issuer.get()
+            && *issuer != discovery.issuer
+        {
+            return Err(OidcError::Discovery(format!("issuer changed from {issuer} to {}", discovery.issuer)));
+        }
+
+        let previous = self.current.load_full();
+        let jwks = match &previous {
+            // Keys restored from a snapshot are replaced by a live fetch
+            Some(loaded) if loaded.discovery.jwks_uri == discovery.jwks_uri && loaded.expires_at.is_none() => loaded.jwks.clone(),
+            _ => self.track(JwkCache::new(&self.http_client, &discovery.jwks_uri, self.settings.clone()).await?),
+        };
+
+        match &previous {
+            None => tracing::info!(issuer = %discovery.issuer, "OIDC provider metadata loaded"),
+            Some(loaded) if loaded.discovery != discovery || loaded.expires_at.is_some() => {
+                tracing::info!(issuer = %discovery.issuer, "OIDC provider metadata changed")
+            }
+            Some(_) => tracing::debug!(issuer = %discovery.issuer, "OIDC provider metadata unchanged"),
+        }
+
+        let _ = self.issuer.set(discovery.issuer.clone());
+        self.persist(&discovery, &jwks.published()).await;
+        self.current.store(Some(Arc::new(LoadedMetadata { discovery, jwks, expires_at: None })));
+        Ok(())
+    }
+
+    /// Load the persisted snapshot, if it hasn't expired. Returns its expiry.
+    async fn restore(&self) -> Result<Option<OffsetDateTime>, OidcError> {
+        let Some(snapshot) = self.store.find_valid(&self.issuer_url).await
+            .map_err(|e| OidcError::Internal(format!("loading metadata snapshot: {e}")))?
+        else {
+            return Ok(None);
+        };
+
+        let discovery: OidcDiscovery = serde_json::from_value(snapshot.discovery)?;
+        let published: JwksResponse = serde_json::from_value(snapshot.jwks)?;
+        let jwks = self.track(JwkCache::restore(&self.http_client, &discovery.jwks_uri, self.settings.clone(), published));
+
+        let _ = self.issuer.set(discovery.issuer.clone());
+        self.current.store(Some(Arc::new(LoadedMetadata { discovery, jwks, expires_at: Some(snapshot.expires_at) })));
+        Ok(Some(snapshot.expires_at))
+    }
+
+    /// Start the key cache's refresh loop and persist every key set it fetches, but only
+    /// together with a discovery document that was loaded live and still points to it.
+    /// Keys fetched while running on a snapshot are persisted by the next `fetch`.
+    fn track(&self, jwks: JwkCache) -> Arc<JwkCache> {
+        let this = self.this.clone();
+        let jwks_uri = jwks.jwks_uri().to_string();
+        jwks.on_fetch(move |published| {
+            let Some(metadata) = this.upgrade() else { return };
+            let jwks_uri = jwks_uri.clone();
+            tokio::spawn(async move {
+                let Ok(loaded) = metadata.live() else { return };
+                if loaded.discovery.jwks_uri == jwks_uri {
+                    metadata.persist(&loaded.discovery, &published).await;
+                }
+            });
+        });
+        let jwks = Arc::new(jwks);
+        jwks.spawn_refresh_task();
+        jwks
+    }
+
+    async fn persist(&self, discovery: &OidcDiscovery, jwks: &JwksResponse) {
+        let fetched_at = OffsetDateTime::now_utc();
+        let snapshot = match (serde_json::to_value(discovery), serde_json::to_value(jwks)) {
+            (Ok(discovery), Ok(jwks)) => OidcMetadataSnapshot {
+                issuer_url: self.issuer_url.clone(),
+                discovery,
+                jwks,
+                fetched_at,
+                expires_at: fetched_at + self.settings.snapshot_max_age,
+            },
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::warn!(issuer = %self.issuer_url, error = %e, "Failed to serialize OIDC metadata snapshot");
+                return;
+            }
+        };
+        if let Err(e) = self.store.save(&snapshot).await {
+            tracing::warn!(issuer = %self.issuer_url, error = %e, "Failed to persist OIDC metadata snapshot");
+        }
+    }
+
+    /// Refresh every `discovery_refresh_interval`, retrying sooner while refreshes fail.
+    /// The loop stops once the metadata is dropped.
+    fn spawn_refresh_task(self: &Arc<Self>) {
+        let weak: Weak<Self> = Arc::downgrade(self);
+        let interval = self.settings.discovery_refresh_interval.max(MIN_RETRY_DELAY);
+        let mut retry = MIN_RETRY_DELAY;
+        let mut delay = if self.health().last_error.is_none() { interval } else { retry };
+
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(delay).await;
+
+                let Some(metadata) = weak.upgrade() else { return };
+                match metadata.refresh().await {
+                    Ok(()) => {
+                        retry = MIN_RETRY_DELAY;
+                        delay = interval;
+                    }
+                    Err(e) => {
+                        tracing::warn!(issuer = %metadata.issuer_url, error = %e, retry_in = ?retry, "OIDC metadata refresh failed");
+                        delay = retry;
+                        retry = (retry * 2).min(MAX_RETRY_DELAY.min(interval));
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    /// Nothing listens here, so discovery fails right away.
+    const UNREACHABLE_ISSUER: &str = "http://127.0.0.1:9";
+
+    /// Keeps snapshots in memory and, like the Postgres repository, hides expired ones.
+    #[derive(Default)]
+    struct MemoryStore {
+        snapshot: Mutex<Option<OidcMetadataSnapshot>>,
+    }
+
+    #[async_trait]
+    impl OidcMetadataRepository for MemoryStore {
+        async fn save(&self, snapshot: &OidcMetadataSnapshot) -> Result<(), sqlx::Error> {
+            *self.snapshot.lock().unwrap() = Some(snapshot.clone());
+            Ok(())
+        }
+
+        async fn find_valid(&self, issuer_url: &str) -> Result<Option<OidcMetadataSnapshot>, sqlx::Error> {
+            let snapshot = self.snapshot.lock().unwrap().clone();
+            Ok(snapshot.filter(|s| s.issuer_url == issuer_url && s.expires_at > OffsetDateTime::now_utc()))
+        }
+    }
+
+    fn discovery() -> OidcDiscovery {
+        serde_json::from_value(json!({
+            "issuer": "https://idp.example.com",
+            "authorization_endpoint": "https://idp.example.com/authorize",
+            "token_endpoint": "https://idp.example.com/token",
+            "jwks_uri": "https://idp.example.com/jwks",
+        }))
+        .unwrap()
+    }
+
+    fn jwks() -> JwksResponse {
+        serde_json::from_value(json!({
+            "keys": [{ "kid": "ed", "kty": "OKP", "crv": "Ed25519", "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo" }]
+        }))
+        .unwrap()
+    }
+
+    fn settings(snapshot_max_age: Duration) -> JwksSettings {
+        JwksSettings { snapshot_max_age, ..JwksSettings::default() }
+    }
+
+    #[tokio::test]
+    async fn snapshot_expires_after_max_age() {
+        let store = Arc::new(MemoryStore::default());
+        let max_age = Duration::from_secs(3600);
+        let metadata = ProviderMetadata::load(Client::new(), UNREACHABLE_ISSUER, settings(max_age), store.clone()).await;
+
+        metadata.persist(&discovery(), &jwks()).await;
+
+        let saved = store.snapshot.lock().unwrap().clone().unwrap();
+        assert_eq!(saved.issuer_url, UNREACHABLE_ISSUER);
+        assert_eq!(saved.expires_at - saved.fetched_at, max_age);
+    }
+
+    #[tokio::test]
+    async fn unreachable_issuer_starts_from_persisted_snapshot() {
+        let store = Arc::new(MemoryStore::default());
+        let first = ProviderMetadata::load(Client::new(), UNREACHABLE_ISSUER, settings(Duration::from_secs(3600)), store.clone()).await;
+        assert!(matches!(first.current(), Err(OidcError::Unavailable(_))));
+        first.persist(&discovery(), &jwks()).await;
+
+        let restored = ProviderMetadata::load(Client::new(), UNREACHABLE_ISSUER, settings(Duration::from_secs(3600)), store).await;
+
+        let loaded = restored.current().unwrap();
+        assert_eq!(loaded.discovery, discovery());
+        assert_eq!(serde_json::to_value(&*loaded.jwks.published()).unwrap(), serde_json::to_value(jwks()).unwrap());
+        assert_eq!(restored.issuer(), "https://idp.example.com");
+        assert!(restored.health().snapshot_expires_at.is_some());
+        assert!(matches!(restored.live(), Err(OidcError::Unavailable(_))));
+    }
+
+    #[tokio::test]
+    async fn expired_snapshot_is_not_used() {
+        let store = Arc::new(MemoryStore::default());
+        let first = ProviderMetadata::load(Client::new(), UNREACHABLE_ISSUER, settings(Duration::from_secs(3600)), store.clone()).await;
+        first.persist(&discovery(), &jwks()).await;
+        let restored = ProviderMetadata::load(Client::new(), UNREACHABLE_ISSUER, settings(Duration::from_secs(3600)), store.clone()).await;
+        let loaded = restored.current().unwrap();
+
+        // A snapshot in use stops being trusted once it expires
+        restored.current.store(Some(Arc::new(LoadedMetadata {
+            discovery: loaded.discovery.clone(),
+            jwks: loaded.jwks.clone(),
+            expires_at: Some(OffsetDateTime::now_utc() - time::Duration::seconds(1)),
+        })));
+        assert!(matches!(restored.current(), Err(OidcError::Unavailable(_))));
+        assert!(!restored.health().available);
+
+        // and an expired one isn't restored at all
+        store.snapshot.lock().unwrap().as_mut().unwrap().expires_at = OffsetDateTime::now_utc() - time::Duration::seconds(1);
+        let degraded = ProviderMetadata::load(Client::new(), UNREACHABLE_ISSUER, settings(Duration::from_secs(3600)), store).await;
+        assert!(matches!(degraded.current(), Err(OidcError::Unavailable(_))));
+        assert!(degraded.health().snapshot_expires_at.is_none());
+    }
+}
@@ -1,10 +1,17 @@
 pub mod claims;
+pub mod client_auth;
 pub mod discovery;
+pub mod dpop;
 pub mod error;
+pub mod introspection;
 pub mod jwk;
+pub mod logout;
+pub mod metadata;
 pub mod provider;
 pub mod providers;
+pub mod registry;
+pub mod validation;
 
-pub use claims::OidcClaims;
+pub use claims::{OidcClaims, PrincipalKind};
 pub use error::OidcError;
-pub use provider::RoleMapper;
+pub use provider::{RoleMapper, TokenTypeHint};
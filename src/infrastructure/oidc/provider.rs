@@ -1,23 +1,70 @@
+use crate::application::dto::auth::authorization_request::AuthorizationRequest;
+use crate::application::dto::auth::device_authorization::{DeviceAuthorizationResponse, DeviceTokenPoll};
 use crate::application::dto::auth::token_response::TokenResponse;
-use crate::infrastructure::oidc::claims::OidcClaims;
+use crate::infrastructure::oidc::claims::{OidcClaims, PrincipalKind};
 use crate::infrastructure::oidc::error::OidcError;
+use crate::infrastructure::oidc::logout::LogoutToken;
+use crate::infrastructure::oidc::metadata::ProviderHealth;
+
+/// Kind of token passed to `revoke_token`, sent as `token_type_hint` (RFC 7009 §2.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenTypeHint {
+    AccessToken,
+    RefreshToken,
+}
+
+impl TokenTypeHint {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TokenTypeHint::AccessToken => "access_token",
+            TokenTypeHint::RefreshToken => "refresh_token",
+        }
+    }
+}
 
 #[async_trait::async_trait]
 pub trait OidcProvider: Send + Sync {
-    /// Build an authorization URL. If `code_challenge` is Some, PKCE S256 is used.
-    async fn authorize_url(&self, state: Option<String>, code_challenge: Option<&str>) -> String;
+    /// Issuer identifier as published in the discovery document (matches the `iss` claim).
+    fn issuer(&self) -> &str;
+
+    /// Outcome of the latest discovery refresh.
+    fn health(&self) -> ProviderHealth;
+
+    /// Build an authorization URL. If `request.code_challenge` is Some, PKCE S256 is used.
+    /// Fails with `OidcError::Unavailable` while the provider's metadata isn't loaded.
+    async fn authorize_url(&self, request: &AuthorizationRequest) -> Result<String, OidcError>;
 
     /// Exchange the authorization code for tokens. If `code_verifier` is Some, PKCE is used.
     async fn exchange_code_for_tokens(&self, code: &str, code_verifier: Option<&str>) -> Result<TokenResponse, OidcError>;
 
     async fn refresh_access_token(&self, refresh_token: &str) -> Result<TokenResponse, OidcError>;
 
+    /// Start a device authorization grant (RFC 8628) at the `device_authorization_endpoint`.
+    async fn start_device_authorization(&self) -> Result<DeviceAuthorizationResponse, OidcError>;
+
+    /// Poll the token endpoint once with a device code.
+    async fn poll_device_token(&self, device_code: &str) -> Result<DeviceTokenPoll, OidcError>;
+
+    /// OAuth 2.0 token exchange (RFC 8693): trade `subject_token` (an access token)
+    /// for an access token restricted to `audience`.
+    async fn exchange_token(&self, subject_token: &str, audience: &str) -> Result<TokenResponse, OidcError>;
+
     async fn validate_access_token(&self, token: &str) -> Result<OidcClaims, OidcError>;
 
     async fn validate_id_token(&self, id_token: &str) -> Result<OidcClaims, OidcError>;
 
-    /// Revoke a token at the provider (for proper logout)
-    async fn revoke_token(&self, token: &str) -> Result<(), OidcError>;
+    /// Claims about the user from the `userinfo_endpoint`, for an access token.
+    async fn fetch_userinfo(&self, access_token: &str) -> Result<serde_json::Value, OidcError>;
+
+    /// Revoke a token at the provider (for proper logout). `hint` is omitted when `None`,
+    /// leaving it to the provider to find out which kind of token it is.
+    async fn revoke_token(&self, token: &str, hint: Option<TokenTypeHint>) -> Result<(), OidcError>;
+
+    /// Validate a back-channel logout token (OpenID Connect Back-Channel Logout 1.0).
+    async fn validate_logout_token(&self, token: &str) -> Result<LogoutToken, OidcError>;
+
+    /// RP-initiated logout URL (`end_session_endpoint`), or `None` if the provider has none.
+    fn end_session_url(&self, id_token_hint: Option<&str>) -> Option<String>;
 }
 
 #[async_trait::async_trait]
@@ -34,5 +81,7 @@ pub trait OidcAdminApi: Send + Sync {
 pub struct IdpUser {
     pub idp_subject: String,
     pub username: Option<String>,
-    pub email: Option<String>
+    pub email: Option<String>,
+    /// Human user or machine (service) user
+    pub kind: PrincipalKind,
 }
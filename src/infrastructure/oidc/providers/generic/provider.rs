@@ -0,0 +1,399 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use moka::future::Cache;
+use crate::infrastructure::oidc::{OidcClaims, OidcError, jwk::{decode_jwt_payload, looks_like_jwt, JwksSettings}, provider::OidcProvider, RoleMapper, TokenTypeHint};
+use crate::infrastructure::oidc::metadata::{ProviderHealth, ProviderMetadata};
+use crate::infrastructure::oidc::client_auth::ClientAuth;
+use crate::infrastructure::oidc::introspection::{build_introspection_cache, claims_from_introspection, introspect, token_cache_key};
+use crate::infrastructure::oidc::logout::LogoutToken;
+use crate::infrastructure::oidc::validation::ValidationPolicy;
+use crate::infrastructure::config::OidcProviderConfig;
+use crate::domain::repositories::OidcMetadataRepository;
+use crate::application::dto::auth::authorization_request::AuthorizationRequest;
+use crate::application::dto::auth::device_authorization::{DeviceAuthorizationResponse, DeviceTokenPoll};
+use crate::application::dto::auth::token_response::TokenResponse;
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const TOKEN_EXCHANGE_GRANT: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+/// Standards-based OIDC provider (Keycloak, Authentik, ...). Provider specifics
+/// are limited to the `RoleMapper` used to read roles from the access token.
+pub struct GenericOidcProvider {
+    http_client: Client,
+    client_id: String,
+    redirect_url: String,
+    scopes: String,
+    post_logout_redirect_uri: Option<String>,
+    /// Discovery document and JWKS, refreshed in the background; absent while the issuer is down
+    metadata: Arc<ProviderMetadata>,
+    role_mapper: Arc<dyn RoleMapper>,
+    client_auth: ClientAuth,
+    introspection_cache: Cache<String, OidcClaims>,
+    /// Rules for access tokens (JWT or introspected)
+    access_policy: ValidationPolicy,
+    /// Rules for ID and logout tokens
+    id_policy: ValidationPolicy,
+}
+
+impl GenericOidcProvider {
+    pub async fn new(
+        http_client: Client,
+        pc: &OidcProviderConfig,
+        jwks_settings: JwksSettings,
+        metadata_store: Arc<dyn OidcMetadataRepository>,
+        role_mapper: Arc<dyn RoleMapper>,
+    ) -> Result<Self, OidcError> {
+        let metadata = ProviderMetadata::load(http_client.clone(), &pc.issuer_url, jwks_settings, metadata_store).await;
+        // Client assertions are addressed to the issuer, which equals the configured issuer URL
+        let client_auth = ClientAuth::from_config(pc, metadata.issuer())?;
+
+        Ok(Self {
+            http_client,
+            client_id: pc.client_id.clone(),
+            redirect_url: pc.redirect_url.clone(),
+            scopes: pc.scopes.clone(),
+            post_logout_redirect_uri: pc.post_logout_redirect_uri.clone(),
+            metadata,
+            role_mapper,
+            client_auth,
+            introspection_cache: build_introspection_cache(),
+            access_policy: pc.validation.for_access_tokens(&pc.client_id),
+            id_policy: pc.validation.for_id_tokens(&pc.client_id),
+        })
+    }
+
+    /// Authorization request parameters other than `client_id`, which client authentication adds.
+    fn authorization_params(&self, request: &AuthorizationRequest) -> Vec<(&'static str, String)> {
+        // idp_hint / organization have no standard parameter; providers map them to scopes
+        let scope = std::iter::once(self.scopes.as_str())
+            .chain(request.extra_scopes.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut params = vec![
+            ("response_type", "code".to_string()),
+            ("redirect_uri", self.redirect_url.clone()),
+            ("scope", scope),
+        ];
+        if let Some(s) = &request.state { params.push(("state", s.clone())); }
+        if let Some(n) = &request.nonce { params.push(("nonce", n.clone())); }
+        if let Some(ch) = &request.code_challenge {
+            params.push(("code_challenge", ch.clone()));
+            params.push(("code_challenge_method", "S256".to_string()));
+        }
+        if let Some(acr) = &request.acr_values { params.push(("acr_values", acr.clone())); }
+        if let Some(max_age) = request.max_age { params.push(("max_age", max_age.to_string())); }
+        if let Some(prompt) = &request.prompt { params.push(("prompt", prompt.clone())); }
+        if let Some(hint) = &request.login_hint { params.push(("login_hint", hint.clone())); }
+        if let Some(locales) = &request.ui_locales { params.push(("ui_locales", locales.clone())); }
+        params.push(("response_mode", "query".to_string()));
+        params
+    }
+
+    /// Push the authorization parameters (RFC 9126) and return the `request_uri` to redirect with.
+    async fn push_authorization_request(&self, endpoint: &str, params: Vec<(&'static str, String)>) -> Result<String, OidcError> {
+        #[derive(serde::Deserialize)]
+        struct PushedAuthorizationResponse {
+            request_uri: String,
+        }
+
+        let resp = self.client_auth
+            .form_post(&self.http_client, endpoint, params)?
+            .send()
+            .await
+            .map_err(OidcError::Network)?
+            .error_for_status()
+            .map_err(OidcError::Network)?;
+        let par = resp.json::<PushedAuthorizationResponse>().await.map_err(OidcError::Network)?;
+        Ok(par.request_uri)
+    }
+
+    /// Validate an opaque access token through the introspection endpoint (RFC 7662).
+    /// Active results are cached until the token's `exp`.
+    async fn introspect_access_token(&self, token: &str) -> Result<OidcClaims, OidcError> {
+        let key = token_cache_key(token);
+        if let Some(claims) = self.introspection_cache.get(&key).await {
+            return Ok(claims);
+        }
+
+        let metadata = self.metadata.current()?;
+        let endpoint = metadata.discovery.introspection_endpoint.as_deref()
+            .ok_or_else(|| OidcError::NotImplemented("token introspection (no introspection_endpoint)".into()))?;
+
+        let body = introspect(&self.http_client, &self.client_auth, endpoint, token).await?;
+        let roles = self.role_mapper.extract_roles(&body);
+        let claims = claims_from_introspection(body, &metadata.discovery.issuer, &self.access_policy)?
+            .with_roles(roles);
+
+        self.introspection_cache.insert(key, claims.clone()).await;
+        Ok(claims)
+    }
+}
+
+#[async_trait]
+impl OidcProvider for GenericOidcProvider {
+    fn issuer(&self) -> &str {
+        self.metadata.issuer()
+    }
+
+    fn health(&self) -> ProviderHealth {
+        self.metadata.health()
+    }
+
+    async fn authorize_url(&self, request: &AuthorizationRequest) -> Result<String, OidcError> {
+        // A persisted snapshot can validate tokens, but a login needs the provider to be up
+        let metadata = self.metadata.live()?;
+        let params = self.authorization_params(request);
+        let mut url = Url::parse(&metadata.discovery.authorization_endpoint)
+            .map_err(|e| OidcError::Discovery(format!("invalid authorization_endpoint: {e}")))?;
+
+        // With PAR the parameters go over the back channel and the browser only carries request_uri
+        if let Some(endpoint) = metadata.discovery.pushed_authorization_request_endpoint.as_deref() {
+            match self.push_authorization_request(endpoint, params.clone()).await {
+                Ok(request_uri) => {
+                    url.query_pairs_mut()
+                        .append_pair("client_id", &self.client_id)
+                        .append_pair("request_uri", &request_uri);
+                    return Ok(url.to_string());
+                }
+                // The provider would refuse the front-channel parameters anyway
+                Err(e) if metadata.discovery.require_pushed_authorization_requests => {
+                    return Err(OidcError::Unavailable(format!("pushed authorization request failed: {e}")));
+                }
+                Err(e) => tracing::warn!("Pushed authorization request failed, using query parameters: {}", e),
+            }
+        }
+
+        {
+            let mut qp = url.query_pairs_mut();
+            qp.append_pair("client_id", &self.client_id);
+            for (key, value) in &params {
+                qp.append_pair(key, value);
+            }
+        }
+        Ok(url.to_string())
+    }
+
+    async fn exchange_code_for_tokens(&self, code: &str, code_verifier: Option<&str>) -> Result<TokenResponse, OidcError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code.to_string()),
+            ("redirect_uri", self.redirect_url.clone()),
+        ];
+        if let Some(v) = code_verifier {
+            form.push(("code_verifier", v.to_string()));
+        }
+
+        let resp = self.client_auth
+            .form_post(&self.http_client, &self.metadata.current()?.discovery.token_endpoint, form)?
+            .send()
+            .await
+            .map_err(OidcError::Network)?
+            .error_for_status()
+            .map_err(OidcError::Network)?;
+        let tr = resp.json::<TokenResponse>().await.map_err(OidcError::Network)?;
+        Ok(tr)
+    }
+
+    async fn refresh_access_token(&self, refresh_token: &str) -> Result<TokenResponse, OidcError> {
+        let form = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.to_string()),
+        ];
+
+        let resp = self.client_auth
+            .form_post(&self.http_client, &self.metadata.current()?.discovery.token_endpoint, form)?
+            .send()
+            .await
+            .map_err(OidcError::Network)?
+            .error_for_status()
+            .map_err(OidcError::Network)?;
+        let tr = resp.json::<TokenResponse>().await.map_err(OidcError::Network)?;
+        Ok(tr)
+    }
+
+    async fn start_device_authorization(&self) -> Result<DeviceAuthorizationResponse, OidcError> {
+        let metadata = self.metadata.current()?;
+        let endpoint = metadata.discovery.device_authorization_endpoint.as_deref()
+            .ok_or_else(|| OidcError::NotImplemented("device authorization (no device_authorization_endpoint)".into()))?;
+
+        let form = vec![("scope", self.scopes.clone())];
+
+        let resp = self.client_auth
+            .form_post(&self.http_client, endpoint, form)?
+            .send()
+            .await
+            .map_err(OidcError::Network)?
+            .error_for_status()
+            .map_err(OidcError::Network)?;
+        let dar = resp.json::<DeviceAuthorizationResponse>().await.map_err(OidcError::Network)?;
+        Ok(dar)
+    }
+
+    async fn poll_device_token(&self, device_code: &str) -> Result<DeviceTokenPoll, OidcError> {
+        let form = vec![
+            ("grant_type", DEVICE_CODE_GRANT.to_string()),
+            ("device_code", device_code.to_string()),
+        ];
+
+        let resp = self.client_auth
+            .form_post(&self.http_client, &self.metadata.current()?.discovery.token_endpoint, form)?
+            .send()
+            .await
+            .map_err(OidcError::Network)?;
+
+        if resp.status().is_success() {
+            let tr = resp.json::<TokenResponse>().await.map_err(OidcError::Network)?;
+            return Ok(DeviceTokenPoll::Complete(tr));
+        }
+
+        // RFC 8628 §3.5: pending/slow_down/denied/expired come back as token endpoint errors
+        let status = resp.status();
+        let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+        match body.get("error").and_then(|v| v.as_str()) {
+            Some("authorization_pending") => Ok(DeviceTokenPoll::Pending),
+            Some("slow_down") => Ok(DeviceTokenPoll::SlowDown),
+            Some("access_denied") => Ok(DeviceTokenPoll::Denied),
+            Some("expired_token") => Ok(DeviceTokenPoll::Expired),
+            Some("invalid_grant") => Ok(DeviceTokenPoll::InvalidGrant),
+            error => Err(OidcError::Provider(format!(
+                "device token request failed ({status}): {}",
+                error.unwrap_or("unknown error")
+            ))),
+        }
+    }
+
+    async fn exchange_token(&self, subject_token: &str, audience: &str) -> Result<TokenResponse, OidcError> {
+        let form = vec![
+            ("grant_type", TOKEN_EXCHANGE_GRANT.to_string()),
+            ("subject_token", subject_token.to_string()),
+            ("subject_token_type", ACCESS_TOKEN_TYPE.to_string()),
+            ("requested_token_type", ACCESS_TOKEN_TYPE.to_string()),
+            ("audience", audience.to_string()),
+        ];
+
+        let resp = self.client_auth
+            .form_post(&self.http_client, &self.metadata.current()?.discovery.token_endpoint, form)?
+            .send()
+            .await
+            .map_err(OidcError::Network)?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+            return Err(OidcError::TokenExchange(format!(
+                "token exchange for {audience} failed ({status}): {}",
+                body.get("error").and_then(|v| v.as_str()).unwrap_or("unknown error")
+            )));
+        }
+
+        let tr = resp.json::<TokenResponse>().await.map_err(OidcError::Network)?;
+        Ok(tr)
+    }
+
+    async fn validate_access_token(&self, token: &str) -> Result<OidcClaims, OidcError> {
+        // 0) Opaque (non-JWT) tokens can only be checked by the provider
+        if !looks_like_jwt(token) {
+            return self.introspect_access_token(token).await;
+        }
+
+        // 1) Verify signature & standard claims (exp/aud/iss/…) per the validation policy
+        let metadata = self.metadata.current()?;
+        let claims = metadata
+            .jwks
+            .validate(token, &metadata.discovery, &self.access_policy)
+            .await?;
+
+        // 2) Read provider-specific fields from the *raw* payload
+        let raw = decode_jwt_payload(token)?;
+        Ok(claims.with_roles(self.role_mapper.extract_roles(&raw)))
+    }
+
+    async fn validate_id_token(&self, id_token: &str) -> Result<OidcClaims, OidcError> {
+        let metadata = self.metadata.current()?;
+        metadata.jwks.validate(id_token, &metadata.discovery, &self.id_policy).await
+    }
+
+    async fn validate_logout_token(&self, token: &str) -> Result<LogoutToken, OidcError> {
+        // Logout tokens carry `iat` but `exp` is optional; `events`, `jti`, `sub`/`sid` are checked by LogoutToken
+        let metadata = self.metadata.current()?;
+        let raw = metadata
+            .jwks
+            .verify(token, &metadata.discovery, &self.id_policy, &["iss", "aud", "iat"])
+            .await?;
+        LogoutToken::from_value(&raw)
+    }
+
+    async fn fetch_userinfo(&self, access_token: &str) -> Result<serde_json::Value, OidcError> {
+        let metadata = self.metadata.current()?;
+        let endpoint = metadata.discovery.userinfo_endpoint.as_deref()
+            .ok_or_else(|| OidcError::NotImplemented("userinfo (no userinfo_endpoint)".into()))?;
+
+        let info = self.http_client
+            .get(endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(OidcError::Network)?
+            .error_for_status()
+            .map_err(OidcError::Network)?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(OidcError::Network)?;
+        Ok(info)
+    }
+
+    async fn revoke_token(&self, token: &str, hint: Option<TokenTypeHint>) -> Result<(), OidcError> {
+        // RFC 7009 revocation endpoint from the discovery document
+        let metadata = self.metadata.current()?;
+        let Some(revoke_url) = metadata.discovery.revocation_endpoint.as_deref() else {
+            tracing::warn!("Provider does not advertise a revocation_endpoint, skipping token revocation");
+            return Ok(());
+        };
+
+        let mut form = vec![("token", token.to_string())];
+        if let Some(hint) = hint {
+            form.push(("token_type_hint", hint.as_str().to_string()));
+        }
+
+        // Public PKCE clients only send client_id; confidential clients authenticate
+        let resp = self.client_auth
+            .form_post(&self.http_client, revoke_url, form)?
+            .send()
+            .await
+            .map_err(OidcError::Network)?;
+
+        // RFC 7009: The authorization server responds with HTTP status code 200
+        // if the revocation is successful or if the client submitted an invalid token
+        if resp.status().is_success() {
+            tracing::info!("Token revocation successful");
+            Ok(())
+        } else {
+            tracing::warn!("Token revocation failed with status: {}", resp.status());
+            // Don't treat revocation failures as critical errors since the token
+            // might already be invalid or expired
+            Ok(())
+        }
+    }
+
+    fn end_session_url(&self, id_token_hint: Option<&str>) -> Option<String> {
+        let metadata = self.metadata.current().ok()?;
+        let endpoint = metadata.discovery.end_session_endpoint.as_deref()?;
+        let mut url = Url::parse(endpoint)
+            .inspect_err(|e| tracing::warn!("invalid end_session_endpoint: {}", e))
+            .ok()?;
+        {
+            let mut qp = url.query_pairs_mut();
+            qp.append_pair("client_id", &self.client_id);
+            if let Some(hint) = id_token_hint {
+                qp.append_pair("id_token_hint", hint);
+            }
+            if let Some(uri) = &self.post_logout_redirect_uri {
+                qp.append_pair("post_logout_redirect_uri", uri);
+            }
+        }
+        Some(url.to_string())
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeSet;
+use serde_json::Value;
+use crate::infrastructure::oidc::provider::RoleMapper;
+
+/// Reads roles from one or more configurable claim paths.
+///
+/// A path is either a JSON pointer (`/realm_access/roles`) or a dotted path
+/// (`realm_access.roles`). Dotted paths are first tried as a literal top-level
+/// claim name, so URL- or URN-style claim names containing dots still work.
+///
+/// Array values contribute their string entries; object values contribute the
+/// keys whose value is not `false` or `null` (ZITADEL-style role maps).
+pub struct ClaimPathRoleMapper {
+    paths: Vec<String>,
+}
+
+impl ClaimPathRoleMapper {
+    pub fn new<I, S>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self { paths: paths.into_iter().map(Into::into).collect() }
+    }
+
+    /// Parse a comma-separated list of claim paths, e.g. `realm_access.roles,groups`.
+    pub fn from_config(value: &str) -> Self {
+        Self::new(value.split(',').map(str::trim).filter(|s| !s.is_empty()))
+    }
+
+    fn resolve<'a>(claims: &'a Value, path: &str) -> Option<&'a Value> {
+        if path.starts_with('/') {
+            return claims.pointer(path);
+        }
+        if let Some(v) = claims.get(path) {
+            return Some(v);
+        }
+        path.split('.').try_fold(claims, |node, segment| node.get(segment))
+    }
+}
+
+impl RoleMapper for ClaimPathRoleMapper {
+    fn extract_roles(&self, claims: &Value) -> Vec<String> {
+        let mut roles = BTreeSet::new();
+
+        for path in &self.paths {
+            match Self::resolve(claims, path) {
+                Some(Value::Array(items)) => {
+                    roles.extend(items.iter().filter_map(|v| v.as_str()).map(str::to_string));
+                }
+                Some(Value::Object(map)) => {
+                    roles.extend(
+                        map.iter()
+                            .filter(|(_, v)| !matches!(v, Value::Bool(false) | Value::Null))
+                            .map(|(k, _)| k.clone()),
+                    );
+                }
+                Some(Value::String(role)) => {
+                    roles.insert(role.clone());
+                }
+                _ => {}
+            }
+        }
+
+        roles.into_iter().collect()
+    }
+}
@@ -1 +1,64 @@
-pub mod zitadel;
\ No newline at end of file
+pub mod generic;
+pub mod zitadel;
+
+use std::sync::Arc;
+use reqwest::Client;
+use crate::domain::repositories::OidcMetadataRepository;
+use crate::infrastructure::config::{Config, OidcProviderConfig, OidcProviderKind};
+use crate::infrastructure::oidc::{OidcError, RoleMapper};
+use crate::infrastructure::oidc::jwk::JwksSettings;
+use crate::infrastructure::oidc::provider::OidcProvider;
+use crate::infrastructure::oidc::providers::generic::provider::GenericOidcProvider;
+use crate::infrastructure::oidc::providers::generic::role_mapper::ClaimPathRoleMapper;
+use crate::infrastructure::oidc::providers::zitadel::provider::ZitadelProvider;
+use crate::infrastructure::oidc::registry::{OidcProviderRegistry, RegisteredProvider};
+
+/// Default role claim for generic providers when no roles claim is configured.
+const DEFAULT_ROLES_CLAIM: &str = "roles";
+
+/// Build the `OidcProvider` implementation selected by the provider's `kind`.
+pub async fn build_provider(
+    http_client: Client,
+    pc: &OidcProviderConfig,
+    jwks_settings: JwksSettings,
+    metadata_store: Arc<dyn OidcMetadataRepository>,
+) -> Result<Arc<dyn OidcProvider + Send + Sync>, OidcError> {
+    let claim_mapper = pc.roles_claim.as_deref()
+        .map(|claim| Arc::new(ClaimPathRoleMapper::from_config(claim)) as Arc<dyn RoleMapper>);
+
+    let provider: Arc<dyn OidcProvider + Send + Sync> = match pc.kind {
+        OidcProviderKind::Zitadel => Arc::new(
+            ZitadelProvider::new(http_client, pc, jwks_settings, metadata_store, claim_mapper).await?
+        ),
+        OidcProviderKind::Generic => Arc::new(
+            GenericOidcProvider::new(
+                http_client,
+                pc,
+                jwks_settings,
+                metadata_store,
+                claim_mapper.unwrap_or_else(|| Arc::new(ClaimPathRoleMapper::from_config(DEFAULT_ROLES_CLAIM))),
+            ).await?
+        ),
+    };
+
+    tracing::info!(name = %pc.name, kind = ?pc.kind, client_auth = ?pc.client_auth_method, issuer = %provider.issuer(), "OIDC provider initialized");
+    Ok(provider)
+}
+
+/// Build every configured provider, in configuration order. Discovery and JWKS are
+/// persisted to `metadata_store` so a provider can start while its issuer is down.
+pub async fn build_registry(
+    http_client: Client,
+    cfg: &Config,
+    metadata_store: Arc<dyn OidcMetadataRepository>,
+) -> Result<OidcProviderRegistry, OidcError> {
+    let mut providers = Vec::with_capacity(cfg.providers.len());
+    for pc in &cfg.providers {
+        providers.push(RegisteredProvider {
+            name: pc.name.clone(),
+            provider: build_provider(http_client.clone(), pc, cfg.jwks_settings(), metadata_store.clone()).await?,
+            introspect_opaque: pc.introspect_opaque,
+        });
+    }
+    OidcProviderRegistry::new(providers)
+}
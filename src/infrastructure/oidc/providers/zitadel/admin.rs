@@ -1,4 +1,4 @@
-use crate::infrastructure::oidc::OidcError;
+use crate::infrastructure::oidc::{OidcError, PrincipalKind};
 use crate::infrastructure::oidc::provider::{IdpUser, OidcAdminApi};
 use serde::Deserialize;
 use async_trait::async_trait;
@@ -26,6 +26,13 @@ struct UserV2 {
     login_names: Option<Vec<String>>,
     #[serde(rename = "human")]
     human_user: Option<HumanUser>,
+    #[serde(rename = "machine")]
+    machine_user: Option<MachineUser>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MachineUser {
+    name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -76,10 +83,12 @@ impl OidcAdminApi for ZitadelAdminApi {
                 .json().await.map_err(OidcError::Network)?;
 
             for u in body.result {
-                // Skip service/machine users - only sync human users
-                if u.human_user.is_none() {
-                    continue;
-                }
+                // Service/machine users are synced as agents
+                let kind = match (&u.human_user, &u.machine_user) {
+                    (Some(_), _) => PrincipalKind::Human,
+                    (None, Some(_)) => PrincipalKind::Machine,
+                    (None, None) => continue,
+                };
 
                 // Extract email from nested structure
                 let email = u.human_user
@@ -87,15 +96,17 @@ impl OidcAdminApi for ZitadelAdminApi {
                     .and_then(|h| h.email.as_ref())
                     .and_then(|e| e.email.clone());
 
-                // Use preferred login name, or first login name as fallback
+                // Use preferred login name, or first login name (machine name) as fallback
                 let username = u.preferred_login_name
                     .or_else(|| u.login_names.as_ref()
-                        .and_then(|names| names.first().cloned()));
+                        .and_then(|names| names.first().cloned()))
+                    .or_else(|| u.machine_user.as_ref().and_then(|m| m.name.clone()));
 
                 acc.push(IdpUser {
                     idp_subject: u.user_id,
                     email,
                     username,
+                    kind,
                 });
             }
 
@@ -1,163 +1,102 @@
+use std::sync::Arc;
 use async_trait::async_trait;
-use reqwest::{Client, Url};
-use crate::infrastructure::oidc::{OidcClaims, OidcError, discovery::OidcDiscovery, jwk::JwkCache, provider::OidcProvider, RoleMapper};
-use crate::application::dto::auth::token_response::TokenResponse;
-use base64::{engine::general_purpose, Engine as _};
-use serde_json::Value;
+use reqwest::Client;
+use crate::infrastructure::oidc::{OidcClaims, OidcError, jwk::JwksSettings, provider::OidcProvider, RoleMapper, TokenTypeHint};
+use crate::infrastructure::oidc::logout::LogoutToken;
+use crate::infrastructure::oidc::metadata::ProviderHealth;
+use crate::infrastructure::oidc::providers::generic::provider::GenericOidcProvider;
 use crate::infrastructure::oidc::providers::zitadel::role_mapper::ZitadelRoleMapper;
+use crate::application::dto::auth::authorization_request::AuthorizationRequest;
+use crate::application::dto::auth::device_authorization::{DeviceAuthorizationResponse, DeviceTokenPoll};
+use crate::application::dto::auth::token_response::TokenResponse;
+use crate::infrastructure::config::OidcProviderConfig;
+use crate::domain::repositories::OidcMetadataRepository;
 
+/// ZITADEL flavoured OIDC provider. Protocol handling is shared with
+/// `GenericOidcProvider`; roles come from the `urn:zitadel:iam:org:project:*roles` claims
+/// unless a custom `RoleMapper` is supplied.
 pub struct ZitadelProvider {
-    http_client: Client,
-    client_id: String,
-    redirect_url: String,
-    scopes: String,
-    discovery: OidcDiscovery,
-    jwks: JwkCache,
+    inner: GenericOidcProvider,
 }
 
 impl ZitadelProvider {
     pub async fn new(
         http_client: Client,
-        issuer_url: &str,
-        client_id: &str,
-        redirect_url: &str,
-        scopes: &str,
+        pc: &OidcProviderConfig,
+        jwks_settings: JwksSettings,
+        metadata_store: Arc<dyn OidcMetadataRepository>,
+        role_mapper: Option<Arc<dyn RoleMapper>>,
     ) -> Result<Self, OidcError> {
-        let discovery = OidcDiscovery::fetch(&http_client,issuer_url).await?;
-        let jwks = JwkCache::new(&http_client,&discovery.jwks_uri).await?;
-
-        Ok(Self {
-            http_client,
-            client_id: client_id.to_string(),
-            redirect_url: redirect_url.to_string(),
-            scopes: scopes.to_string(),
-            discovery,
-            jwks,
-        })
-    }
-}
+        let role_mapper = role_mapper.unwrap_or_else(|| Arc::new(ZitadelRoleMapper));
+        let inner = GenericOidcProvider::new(http_client, pc, jwks_settings, metadata_store, role_mapper).await?;
 
-fn decode_jwt_payload(token: &str) -> Result<Value, OidcError> {
-    let payload_b64 = token.split('.').nth(1).ok_or_else(|| OidcError::Jwt("malformed JWT".into()))?;
-    let bytes = general_purpose::URL_SAFE_NO_PAD
-        .decode(payload_b64)
-        .map_err(|e| OidcError::Jwt(format!("payload b64 decode: {e}")))?;
-    serde_json::from_slice::<Value>(&bytes).map_err(OidcError::Json)
+        Ok(Self { inner })
+    }
 }
 
-
 #[async_trait]
 impl OidcProvider for ZitadelProvider {
-    async fn authorize_url(&self, state: Option<String>, code_challenge: Option<&str>) -> String {
-        let mut url = Url::parse(&self.discovery.authorization_endpoint)
-            .expect("invalid authorization_endpoint");
-        {
-            let mut qp = url.query_pairs_mut();
-            qp.append_pair("client_id", &self.client_id);
-            qp.append_pair("response_type", "code");
-            qp.append_pair("redirect_uri", &self.redirect_url);
-            qp.append_pair("scope", &self.scopes);
-            if let Some(s) = state { qp.append_pair("state", &s); }
-            if let Some(ch) = code_challenge {
-                qp.append_pair("code_challenge", ch);
-                qp.append_pair("code_challenge_method", "S256");
-            }
-            qp.append_pair("response_mode", "query");
-        }
-        url.to_string()
+    fn issuer(&self) -> &str {
+        self.inner.issuer()
     }
 
-    async fn exchange_code_for_tokens(&self, code: &str, code_verifier: Option<&str>) -> Result<TokenResponse, OidcError> {
-        let mut form = vec![
-            ("grant_type", "authorization_code".to_string()),
-            ("code", code.to_string()),
-            ("redirect_uri", self.redirect_url.clone()),
-            ("client_id", self.client_id.clone()),
-        ];
-        if let Some(v) = code_verifier {
-            form.push(("code_verifier".into(), v.to_string()));
+    fn health(&self) -> ProviderHealth {
+        self.inner.health()
+    }
+
+    async fn authorize_url(&self, request: &AuthorizationRequest) -> Result<String, OidcError> {
+        // ZITADEL takes organization and IdP hints as reserved scopes
+        let mut request = request.clone();
+        if let Some(org) = &request.organization {
+            request.extra_scopes.push(format!("urn:zitadel:iam:org:id:{org}"));
         }
+        if let Some(idp) = &request.idp_hint {
+            request.extra_scopes.push(format!("urn:zitadel:iam:org:idp:id:{idp}"));
+        }
+        self.inner.authorize_url(&request).await
+    }
 
-        let resp = self.http_client
-            .post(&self.discovery.token_endpoint)
-            .form(&form)
-            .send()
-            .await
-            .map_err(OidcError::Network)?
-            .error_for_status()
-            .map_err(OidcError::Network)?;
-        let tr = resp.json::<TokenResponse>().await.map_err(OidcError::Network)?;
-        Ok(tr)
+    async fn exchange_code_for_tokens(&self, code: &str, code_verifier: Option<&str>) -> Result<TokenResponse, OidcError> {
+        self.inner.exchange_code_for_tokens(code, code_verifier).await
     }
 
     async fn refresh_access_token(&self, refresh_token: &str) -> Result<TokenResponse, OidcError> {
-        let form = vec![
-            ("grant_type", "refresh_token".to_string()),
-            ("refresh_token", refresh_token.to_string()),
-            ("client_id", self.client_id.clone()),
-        ];
-
-        let resp = self.http_client
-            .post(&self.discovery.token_endpoint)
-            .form(&form)
-            .send()
-            .await
-            .map_err(OidcError::Network)?
-            .error_for_status()
-            .map_err(OidcError::Network)?;
-        let tr = resp.json::<TokenResponse>().await.map_err(OidcError::Network)?;
-        Ok(tr)
+        self.inner.refresh_access_token(refresh_token).await
     }
 
-    async fn validate_access_token(&self, token: &str) -> Result<OidcClaims, OidcError> {
-        // 1) Verify signature & standard claims (exp/aud/iss/…)
-        let mut claims = self
-            .jwks
-            .validate(token, &self.discovery, Some(&self.client_id))?;
+    async fn start_device_authorization(&self) -> Result<DeviceAuthorizationResponse, OidcError> {
+        self.inner.start_device_authorization().await
+    }
 
-        // 2) Read provider-specific fields from the *raw* payload
-        let raw = decode_jwt_payload(token)?;
-        let mapper = ZitadelRoleMapper;
-        claims.roles = mapper.extract_roles(&raw);
+    async fn poll_device_token(&self, device_code: &str) -> Result<DeviceTokenPoll, OidcError> {
+        self.inner.poll_device_token(device_code).await
+    }
 
-        Ok(claims)
+    async fn exchange_token(&self, subject_token: &str, audience: &str) -> Result<TokenResponse, OidcError> {
+        self.inner.exchange_token(subject_token, audience).await
+    }
+
+    async fn validate_access_token(&self, token: &str) -> Result<OidcClaims, OidcError> {
+        self.inner.validate_access_token(token).await
     }
 
     async fn validate_id_token(&self, id_token: &str) -> Result<OidcClaims, OidcError> {
-        // Reuse your JWKS validator but force the audience to client_id
-        self.jwks.validate(id_token, &self.discovery, Some(&self.client_id))
+        self.inner.validate_id_token(id_token).await
     }
 
-    async fn revoke_token(&self, token: &str) -> Result<(), OidcError> {
-        // Use Zitadel's revocation endpoint (RFC 7009)
-        let revoke_url = format!("{}/oauth/v2/revoke", self.discovery.issuer.trim_end_matches('/'));
-
-        let mut form = vec![
-            ("token", token),
-            ("client_id", &self.client_id),
-        ];
-
-        // If client secret is available, add it (though for public clients it might not be needed)
-        // For PKCE flows, client_secret might not be required
-        form.push(("token_type_hint", "refresh_token"));
-
-        let resp = self.http_client
-            .post(&revoke_url)
-            .form(&form)
-            .send()
-            .await
-            .map_err(OidcError::Network)?;
-
-        // RFC 7009: The authorization server responds with HTTP status code 200
-        // if the revocation is successful or if the client submitted an invalid token
-        if resp.status().is_success() {
-            tracing::info!("Token revocation successful");
-            Ok(())
-        } else {
-            tracing::warn!("Token revocation failed with status: {}", resp.status());
-            // Don't treat revocation failures as critical errors since the token
-            // might already be invalid or expired
-            Ok(())
-        }
+    async fn fetch_userinfo(&self, access_token: &str) -> Result<serde_json::Value, OidcError> {
+        self.inner.fetch_userinfo(access_token).await
+    }
+
+    async fn revoke_token(&self, token: &str, hint: Option<TokenTypeHint>) -> Result<(), OidcError> {
+        self.inner.revoke_token(token, hint).await
+    }
+
+    async fn validate_logout_token(&self, token: &str) -> Result<LogoutToken, OidcError> {
+        self.inner.validate_logout_token(token).await
+    }
+
+    fn end_session_url(&self, id_token_hint: Option<&str>) -> Option<String> {
+        self.inner.end_session_url(id_token_hint)
     }
 }
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use crate::infrastructure::oidc::error::OidcError;
+use crate::infrastructure::oidc::jwk::decode_jwt_payload;
+use crate::infrastructure::oidc::provider::OidcProvider;
+
+/// A configured provider together with the name it is addressed by.
+#[derive(Clone)]
+pub struct RegisteredProvider {
+    pub name: String,
+    pub provider: Arc<dyn OidcProvider + Send + Sync>,
+    /// Opaque bearer tokens are introspected here (`OIDC_<NAME>_INTROSPECT_OPAQUE`)
+    pub introspect_opaque: bool,
+}
+
+/// All trusted OIDC providers. The first registered provider is the default
+/// for the browser login flow.
+#[derive(Clone)]
+pub struct OidcProviderRegistry {
+    providers: Vec<RegisteredProvider>,
+}
+
+impl OidcProviderRegistry {
+    pub fn new(providers: Vec<RegisteredProvider>) -> Result<Self, OidcError> {
+        if providers.is_empty() {
+            return Err(OidcError::Internal("at least one OIDC provider is required".into()));
+        }
+        Ok(Self { providers })
+    }
+
+    pub fn default_provider(&self) -> &RegisteredProvider {
+        &self.providers[0]
+    }
+
+    pub fn all(&self) -> &[RegisteredProvider] {
+        &self.providers
+    }
+
+    /// Look up a provider by name, `None` selects the default provider.
+    pub fn get(&self, name: Option<&str>) -> Result<&RegisteredProvider, OidcError> {
+        match name {
+            None => Ok(self.default_provider()),
+            Some(name) => self.providers
+                .iter()
+                .find(|p| p.name == name)
+                .ok_or_else(|| OidcError::UnknownProvider(name.to_string())),
+        }
+    }
+
+    /// Like `get`, but `None` only selects the default provider when it is the only one.
+    /// Used where sending a token to the wrong provider would disclose it (cookie refresh).
+    pub fn get_explicit(&self, name: Option<&str>) -> Result<&RegisteredProvider, OidcError> {
+        if name.is_none() && self.providers.len() > 1 {
+            return Err(OidcError::UnknownProvider("no provider given and several are configured".into()));
+        }
+        self.get(name)
+    }
+
+    pub fn by_issuer(&self, issuer: &str) -> Option<&RegisteredProvider> {
+        let issuer = issuer.trim_end_matches('/');
+        self.providers
+            .iter()
+            .find(|p| p.provider.issuer().trim_end_matches('/') == issuer)
+    }
+
+    /// Pick the provider that issued `token`, based on its (not yet verified) `iss` claim.
+    /// The selected provider still verifies signature and issuer.
+    pub fn for_token(&self, token: &str) -> Result<&RegisteredProvider, OidcError> {
+        let payload = decode_jwt_payload(token)?;
+        let iss = payload.get("iss")
+            .and_then(|v| v.as_str())
+            .ok_or(OidcError::MissingClaim("iss"))?;
+
+        self.by_issuer(iss)
+            .ok_or_else(|| OidcError::InvalidClaim("iss", format!("untrusted issuer {iss}")))
+    }
+}
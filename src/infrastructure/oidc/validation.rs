@@ -0,0 +1,227 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use jsonwebtoken::{Algorithm, Validation};
+use serde::Deserialize;
+use serde_json::Value;
+use crate::infrastructure::oidc::error::OidcError;
+
+/// Rules every validated token (access, ID, logout, introspected) is held to,
+/// on top of signature and issuer.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidationPolicy {
+    /// Allowed clock skew for `exp`, `nbf` and `iat`
+    pub leeway_secs: u64,
+    /// Maximum age since `iat`; `None` disables the check
+    pub max_age_secs: Option<u64>,
+    /// Claims that must be present in addition to the token type's own requirements
+    pub required_claims: Vec<String>,
+    /// Accepted `aud` values; empty means the provider's client id
+    pub audiences: Vec<String>,
+    /// Accepted `azp` (or `client_id`) values when the token carries one; empty disables the check
+    pub authorized_parties: Vec<String>,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self {
+            leeway_secs: 60,
+            max_age_secs: Some(24 * 60 * 60),
+            required_claims: Vec::new(),
+            audiences: Vec::new(),
+            authorized_parties: Vec::new(),
+        }
+    }
+}
+
+impl ValidationPolicy {
+    /// Policy for access tokens of the client `client_id`.
+    pub fn for_access_tokens(&self, client_id: &str) -> Self {
+        let mut policy = self.clone();
+        if policy.audiences.is_empty() {
+            policy.audiences.push(client_id.to_string());
+        }
+        policy
+    }
+
+    /// Policy for ID and logout tokens, which are always addressed to the client
+    /// itself (OIDC Core §3.1.3.7): `aud` and a present `azp` must be `client_id`.
+    pub fn for_id_tokens(&self, client_id: &str) -> Self {
+        Self {
+            audiences: vec![client_id.to_string()],
+            authorized_parties: vec![client_id.to_string()],
+            ..self.clone()
+        }
+    }
+
+    /// `jsonwebtoken` settings for signature, issuer, audience and expiry checks.
+    /// `required_claims` are the spec claims the token type needs (e.g. `exp`).
+    pub fn jwt_validation(&self, alg: Algorithm, issuer: &str, required_claims: &[&str]) -> Validation {
+        let mut validation = Validation::new(alg);
+        validation.leeway = self.leeway_secs;
+        validation.set_issuer(&[issuer]);
+        validation.set_required_spec_claims(required_claims);
+        if self.audiences.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.audiences);
+        }
+        validation
+    }
+
+    /// Checks applied to the claim set of any token, JWT or introspected.
+    pub fn check_claims(&self, claims: &Value) -> Result<(), OidcError> {
+        if let Some(missing) = self.required_claims.iter().find(|c| claims.get(c.as_str()).is_none()) {
+            return Err(OidcError::Jwt(format!("missing required claim {missing}")));
+        }
+
+        if !self.audiences.is_empty() {
+            let matches = match claims.get("aud") {
+                Some(Value::String(s)) => self.audiences.contains(s),
+                Some(Value::Array(arr)) => arr.iter()
+                    .filter_map(|v| v.as_str())
+                    .any(|aud| self.audiences.iter().any(|a| a == aud)),
+                // `aud` is optional in introspection responses: accept the token only if the
+                // client it was issued to is one we trust
+                _ => claims.get("client_id").or_else(|| claims.get("azp"))
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|client| self.audiences.iter().chain(&self.authorized_parties).any(|a| a == client)),
+            };
+            if !matches {
+                return Err(OidcError::InvalidClaim("aud", "audience mismatch".into()));
+            }
+        }
+
+        if !self.authorized_parties.is_empty()
+            && let Some(azp) = claims.get("azp").or_else(|| claims.get("client_id")).and_then(|v| v.as_str())
+            && !self.authorized_parties.iter().any(|p| p == azp)
+        {
+            return Err(OidcError::InvalidClaim("azp", azp.to_string()));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        if let Some(exp) = claims.get("exp").and_then(|v| v.as_u64())
+            && exp + self.leeway_secs <= now
+        {
+            return Err(OidcError::Jwt("token expired".into()));
+        }
+
+        if let Some(iat) = claims.get("iat").and_then(|v| v.as_u64()) {
+            if iat > now + self.leeway_secs {
+                return Err(OidcError::InvalidClaim("iat", "issued in the future".into()));
+            }
+            if let Some(max_age) = self.max_age_secs
+                && now.saturating_sub(iat) > max_age + self.leeway_secs
+            {
+                return Err(OidcError::Jwt("token too old".into()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn policy() -> ValidationPolicy {
+        ValidationPolicy {
+            leeway_secs: 60,
+            max_age_secs: Some(3600),
+            required_claims: Vec::new(),
+            audiences: vec!["api".into()],
+            authorized_parties: Vec::new(),
+        }
+    }
+
+    fn token(extra: Value) -> Value {
+        let mut claims = json!({ "sub": "u1", "aud": "api", "iat": now(), "exp": now() + 300 });
+        claims.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        claims
+    }
+
+    #[test]
+    fn accepts_a_valid_token() {
+        assert!(policy().check_claims(&token(json!({}))).is_ok());
+    }
+
+    #[test]
+    fn expiry_honours_leeway() {
+        assert!(policy().check_claims(&token(json!({ "exp": now() - 30 }))).is_ok());
+        assert!(policy().check_claims(&token(json!({ "exp": now() - 61 }))).is_err());
+    }
+
+    #[test]
+    fn iat_in_the_future_honours_leeway() {
+        assert!(policy().check_claims(&token(json!({ "iat": now() + 30 }))).is_ok());
+        assert!(policy().check_claims(&token(json!({ "iat": now() + 120 }))).is_err());
+    }
+
+    #[test]
+    fn max_age_is_enforced_and_can_be_disabled() {
+        let old = token(json!({ "iat": now() - 3600 - 120 }));
+        assert!(policy().check_claims(&token(json!({ "iat": now() - 3600 }))).is_ok());
+        assert!(policy().check_claims(&old).is_err());
+
+        let unlimited = ValidationPolicy { max_age_secs: None, ..policy() };
+        assert!(unlimited.check_claims(&old).is_ok());
+    }
+
+    #[test]
+    fn required_claims_must_be_present() {
+        let strict = ValidationPolicy { required_claims: vec!["sid".into()], ..policy() };
+        assert!(strict.check_claims(&token(json!({}))).is_err());
+        assert!(strict.check_claims(&token(json!({ "sid": "s1" }))).is_ok());
+    }
+
+    #[test]
+    fn audience_may_be_an_array() {
+        assert!(policy().check_claims(&token(json!({ "aud": ["other", "api"] }))).is_ok());
+        assert!(policy().check_claims(&token(json!({ "aud": ["other", "more"] }))).is_err());
+        assert!(policy().check_claims(&token(json!({ "aud": "other" }))).is_err());
+    }
+
+    #[test]
+    fn absent_audience_needs_a_trusted_client() {
+        let mut claims = token(json!({}));
+        claims.as_object_mut().unwrap().remove("aud");
+        assert!(policy().check_claims(&claims).is_err());
+
+        claims["client_id"] = json!("someone-else");
+        assert!(policy().check_claims(&claims).is_err());
+
+        claims["client_id"] = json!("api");
+        assert!(policy().check_claims(&claims).is_ok());
+
+        let parties = ValidationPolicy { authorized_parties: vec!["spa".into()], ..policy() };
+        claims["client_id"] = json!("spa");
+        assert!(parties.check_claims(&claims).is_ok());
+    }
+
+    #[test]
+    fn azp_takes_precedence_over_client_id() {
+        let parties = ValidationPolicy { authorized_parties: vec!["spa".into()], ..policy() };
+        assert!(parties.check_claims(&token(json!({ "azp": "spa", "client_id": "other" }))).is_ok());
+        assert!(parties.check_claims(&token(json!({ "azp": "other", "client_id": "spa" }))).is_err());
+        assert!(parties.check_claims(&token(json!({ "client_id": "other" }))).is_err());
+        assert!(parties.check_claims(&token(json!({ "client_id": "spa" }))).is_ok());
+        // Only checked when the token names a party
+        assert!(parties.check_claims(&token(json!({}))).is_ok());
+    }
+
+    #[test]
+    fn id_token_policy_pins_the_client() {
+        let id = policy().for_id_tokens("client");
+        assert!(id.check_claims(&token(json!({ "aud": "client", "azp": "client" }))).is_ok());
+        assert!(id.check_claims(&token(json!({ "aud": "client", "azp": "other" }))).is_err());
+        assert!(id.check_claims(&token(json!({ "aud": "api" }))).is_err());
+    }
+}
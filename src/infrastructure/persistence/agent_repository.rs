@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+use crate::domain::entities::Agent;
+use crate::domain::repositories::AgentRepository;
+
+pub struct PgAgentRepo {
+    pool: Arc<PgPool>,
+}
+
+impl PgAgentRepo {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AgentRepository for PgAgentRepo {
+    async fn find_by_subject(&self, issuer: &str, subject: &str) -> Result<Option<Agent>, Error> {
+        sqlx::query_as!(
+            Agent,
+            r#"
+            SELECT * FROM agents
+            WHERE idp_issuer = $1 AND idp_subject = $2
+            "#,
+            issuer,
+            subject
+        )
+            .fetch_optional(&*self.pool)
+            .await
+    }
+
+    async fn upsert_agent(
+        &self,
+        issuer: &str,
+        subject: &str,
+        name: &str,
+        client_id: Option<&str>,
+        seen: bool,
+    ) -> Result<Agent, Error> {
+        sqlx::query_as!(
+            Agent,
+            r#"
+            INSERT INTO agents (id, idp_issuer, idp_subject, name, client_id, last_seen_at)
+            VALUES ($1, $2, $3, $4, $5, CASE WHEN $6 THEN now() END)
+            ON CONFLICT (idp_issuer, idp_subject)
+            DO UPDATE SET
+                name = EXCLUDED.name,
+                client_id = COALESCE(EXCLUDED.client_id, agents.client_id),
+                last_seen_at = COALESCE(EXCLUDED.last_seen_at, agents.last_seen_at),
+                updated_at = now()
+            RETURNING *
+            "#,
+            Uuid::new_v4(),
+            issuer,
+            subject,
+            name,
+            client_id,
+            seen
+        )
+            .fetch_one(&*self.pool)
+            .await
+    }
+
+    async fn get_all_agents(&self) -> Result<Vec<Agent>, Error> {
+        sqlx::query_as!(
+            Agent,
+            r#"
+            SELECT * FROM agents
+            "#
+        )
+            .fetch_all(&*self.pool)
+            .await
+    }
+}
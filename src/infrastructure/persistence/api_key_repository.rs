@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+use crate::domain::entities::ApiKey;
+use crate::domain::repositories::ApiKeyRepository;
+
+pub struct PgApiKeyRepo {
+    pool: Arc<PgPool>,
+}
+
+impl PgApiKeyRepo {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for PgApiKeyRepo {
+    async fn create(&self, key: &ApiKey) -> Result<ApiKey, Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            INSERT INTO api_keys (id, user_id, name, prefix, key_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+            key.id,
+            key.user_id,
+            key.name,
+            key.prefix,
+            key.key_hash,
+            &key.scopes,
+            key.expires_at
+        )
+            .fetch_one(&*self.pool)
+            .await
+    }
+
+    async fn list_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<ApiKey>, Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT * FROM api_keys
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+            .fetch_all(&*self.pool)
+            .await
+    }
+
+    async fn find_active_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>, Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT * FROM api_keys
+            WHERE prefix = $1
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > now())
+            "#,
+            prefix
+        )
+            .fetch_optional(&*self.pool)
+            .await
+    }
+
+    async fn rename(&self, user_id: uuid::Uuid, id: uuid::Uuid, name: &str) -> Result<Option<ApiKey>, Error> {
+        sqlx::query_as!(
+            ApiKey,
+            r#"
+            UPDATE api_keys
+            SET name = $3, updated_at = now()
+            WHERE id = $2 AND user_id = $1 AND revoked_at IS NULL
+            RETURNING *
+            "#,
+            user_id,
+            id,
+            name
+        )
+            .fetch_optional(&*self.pool)
+            .await
+    }
+
+    async fn revoke(&self, user_id: uuid::Uuid, id: uuid::Uuid) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET revoked_at = now(), updated_at = now()
+            WHERE id = $2 AND user_id = $1 AND revoked_at IS NULL
+            "#,
+            user_id,
+            id
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn touch_last_used(&self, id: uuid::Uuid) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET last_used_at = now()
+            WHERE id = $1
+              AND (last_used_at IS NULL OR last_used_at < now() - interval '1 minute')
+            "#,
+            id
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+}
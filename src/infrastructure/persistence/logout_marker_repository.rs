@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+use crate::domain::entities::LogoutMarker;
+use crate::domain::repositories::LogoutMarkerRepository;
+
+pub struct PgLogoutMarkerRepo {
+    pool: Arc<PgPool>,
+}
+
+impl PgLogoutMarkerRepo {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl LogoutMarkerRepository for PgLogoutMarkerRepo {
+    async fn create(&self, marker: &LogoutMarker) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO logout_markers (id, issuer, subject, sid, logged_out_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            marker.id,
+            marker.issuer,
+            marker.subject,
+            marker.sid,
+            marker.logged_out_at,
+            marker.expires_at
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn latest_for_subject(&self, issuer: &str, subject: &str) -> Result<Option<time::OffsetDateTime>, Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT max(logged_out_at) FROM logout_markers
+            WHERE issuer = $1 AND subject = $2 AND expires_at > now()
+            "#,
+            issuer,
+            subject
+        )
+            .fetch_one(&*self.pool)
+            .await
+    }
+
+    async fn exists_for_sid(&self, issuer: &str, sid: &str) -> Result<bool, Error> {
+        let exists = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM logout_markers
+                WHERE issuer = $1 AND sid = $2 AND expires_at > now()
+            ) AS "exists!"
+            "#,
+            issuer,
+            sid
+        )
+            .fetch_one(&*self.pool)
+            .await?;
+        Ok(exists)
+    }
+
+    async fn record_token_id(&self, issuer: &str, jti: &str, expires_at: time::OffsetDateTime) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO logout_token_ids (issuer, jti, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (issuer, jti) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            WHERE logout_token_ids.expires_at <= now()
+            "#,
+            issuer,
+            jti,
+            expires_at
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn forget_token_id(&self, issuer: &str, jti: &str) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM logout_token_ids
+            WHERE issuer = $1 AND jti = $2
+            "#,
+            issuer,
+            jti
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self) -> Result<u64, Error> {
+        let markers = sqlx::query!(
+            r#"
+            DELETE FROM logout_markers
+            WHERE expires_at <= now()
+            "#
+        )
+            .execute(&*self.pool)
+            .await?;
+        let token_ids = sqlx::query!(
+            r#"
+            DELETE FROM logout_token_ids
+            WHERE expires_at <= now()
+            "#
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(markers.rows_affected() + token_ids.rows_affected())
+    }
+}
@@ -1,4 +1,18 @@
 pub mod user_repository;
+pub mod session_repository;
+pub mod logout_marker_repository;
+pub mod agent_repository;
+pub mod api_key_repository;
+pub mod refresh_token_repository;
+pub mod security_event_repository;
+pub mod oidc_metadata_repository;
 
 pub use user_repository::PgUserRepo;
-pub use crate::domain::repositories::UserRepository;
+pub use session_repository::PgSessionRepo;
+pub use logout_marker_repository::PgLogoutMarkerRepo;
+pub use agent_repository::PgAgentRepo;
+pub use api_key_repository::PgApiKeyRepo;
+pub use refresh_token_repository::PgRefreshTokenRepo;
+pub use security_event_repository::PgSecurityEventRepo;
+pub use oidc_metadata_repository::PgOidcMetadataRepo;
+pub use crate::domain::repositories::{AgentRepository, ApiKeyRepository, LogoutMarkerRepository, OidcMetadataRepository, RefreshTokenRepository, SecurityEventRepository, SessionRepository, UserRepository};
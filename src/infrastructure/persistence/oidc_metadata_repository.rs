@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+use crate::domain::entities::OidcMetadataSnapshot;
+use crate::domain::repositories::OidcMetadataRepository;
+
+pub struct PgOidcMetadataRepo {
+    pool: Arc<PgPool>,
+}
+
+impl PgOidcMetadataRepo {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OidcMetadataRepository for PgOidcMetadataRepo {
+    async fn save(&self, snapshot: &OidcMetadataSnapshot) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO oidc_metadata_snapshots (issuer_url, discovery, jwks, fetched_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (issuer_url) DO UPDATE
+            SET discovery = EXCLUDED.discovery,
+                jwks = EXCLUDED.jwks,
+                fetched_at = EXCLUDED.fetched_at,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            snapshot.issuer_url,
+            snapshot.discovery,
+            snapshot.jwks,
+            snapshot.fetched_at,
+            snapshot.expires_at
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn find_valid(&self, issuer_url: &str) -> Result<Option<OidcMetadataSnapshot>, Error> {
+        sqlx::query_as!(
+            OidcMetadataSnapshot,
+            r#"
+            SELECT issuer_url, discovery, jwks, fetched_at, expires_at
+            FROM oidc_metadata_snapshots
+            WHERE issuer_url = $1 AND expires_at > now()
+            "#,
+            issuer_url
+        )
+            .fetch_optional(&*self.pool)
+            .await
+    }
+}
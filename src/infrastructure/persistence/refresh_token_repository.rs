@@ -0,0 +1,192 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+use crate::domain::entities::{RefreshTokenFamily, RefreshTokenRecord};
+use crate::domain::repositories::RefreshTokenRepository;
+
+pub struct PgRefreshTokenRepo {
+    pool: Arc<PgPool>,
+}
+
+impl PgRefreshTokenRepo {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for PgRefreshTokenRepo {
+    async fn create_family(&self, family: &RefreshTokenFamily, token_hash: &str) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_token_families (id, provider, current_token, created_at, updated_at, issuer, subject, sid)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            family.id,
+            family.provider,
+            family.current_token,
+            family.created_at,
+            family.updated_at,
+            family.issuer,
+            family.subject,
+            family.sid
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, family_id)
+            VALUES ($1, $2)
+            ON CONFLICT (token_hash) DO NOTHING
+            "#,
+            token_hash,
+            family.id
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+
+    async fn find_family(&self, id: uuid::Uuid) -> Result<Option<RefreshTokenFamily>, Error> {
+        sqlx::query_as!(
+            RefreshTokenFamily,
+            r#"SELECT * FROM refresh_token_families WHERE id = $1"#,
+            id
+        )
+            .fetch_optional(&*self.pool)
+            .await
+    }
+
+    async fn find_token(&self, token_hash: &str) -> Result<Option<RefreshTokenRecord>, Error> {
+        sqlx::query_as!(
+            RefreshTokenRecord,
+            r#"SELECT * FROM refresh_tokens WHERE token_hash = $1"#,
+            token_hash
+        )
+            .fetch_optional(&*self.pool)
+            .await
+    }
+
+    async fn rotate(
+        &self,
+        family_id: uuid::Uuid,
+        old_hash: &str,
+        new_hash: &str,
+        current_token: Option<&[u8]>,
+    ) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let rotated = sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET rotated_at = now()
+            WHERE token_hash = $1 AND family_id = $2 AND rotated_at IS NULL
+            "#,
+            old_hash,
+            family_id
+        )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() > 0;
+
+        if !rotated {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, family_id)
+            VALUES ($1, $2)
+            ON CONFLICT (token_hash) DO NOTHING
+            "#,
+            new_hash,
+            family_id
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE refresh_token_families
+            SET current_token = $2, updated_at = now()
+            WHERE id = $1
+            "#,
+            family_id,
+            current_token
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn revoke_family(&self, id: uuid::Uuid) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_token_families
+            SET revoked_at = now(), current_token = NULL, updated_at = now()
+            WHERE id = $1 AND revoked_at IS NULL
+            "#,
+            id
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn bind_login(&self, id: uuid::Uuid, issuer: &str, subject: &str, sid: Option<&str>) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_token_families
+            SET issuer = $2, subject = $3, sid = $4, updated_at = now()
+            WHERE id = $1 AND issuer IS NULL
+            "#,
+            id,
+            issuer,
+            subject,
+            sid
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn adoption_started_at(&self) -> Result<Option<time::OffsetDateTime>, Error> {
+        sqlx::query_scalar!(r#"SELECT min(started_at) FROM refresh_token_adoption"#)
+            .fetch_one(&*self.pool)
+            .await
+    }
+
+    async fn revoke_for_login(&self, issuer: &str, subject: Option<&str>, sid: Option<&str>) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE refresh_token_families
+            SET revoked_at = now(), current_token = NULL, updated_at = now()
+            WHERE issuer = $1
+              AND (sid = $3 OR ($3::text IS NULL AND subject = $2))
+              AND revoked_at IS NULL
+            "#,
+            issuer,
+            subject,
+            sid
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_stale(&self, before: time::OffsetDateTime) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            r#"DELETE FROM refresh_token_families WHERE updated_at < $1"#,
+            before
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+use crate::domain::entities::SecurityEvent;
+use crate::domain::repositories::SecurityEventRepository;
+
+pub struct PgSecurityEventRepo {
+    pool: Arc<PgPool>,
+}
+
+impl PgSecurityEventRepo {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SecurityEventRepository for PgSecurityEventRepo {
+    async fn record(&self, event: &SecurityEvent) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO security_events (id, kind, provider, subject, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            event.id,
+            event.kind,
+            event.provider,
+            event.subject,
+            event.details,
+            event.created_at
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+}
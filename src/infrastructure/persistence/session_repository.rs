@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+use crate::domain::entities::Session;
+use crate::domain::repositories::SessionRepository;
+
+pub struct PgSessionRepo {
+    pool: Arc<PgPool>,
+}
+
+impl PgSessionRepo {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SessionRepository for PgSessionRepo {
+    async fn create(&self, session: &Session) -> Result<Session, Error> {
+        sqlx::query_as!(
+            Session,
+            r#"
+            INSERT INTO sessions (id, user_id, provider, idp_sid, access_token, refresh_token, id_token, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+            session.id,
+            session.user_id,
+            session.provider,
+            session.idp_sid,
+            session.access_token,
+            session.refresh_token,
+            session.id_token,
+            session.expires_at
+        )
+            .fetch_one(&*self.pool)
+            .await
+    }
+
+    async fn find_active(&self, id: uuid::Uuid) -> Result<Option<Session>, Error> {
+        sqlx::query_as!(
+            Session,
+            r#"
+            SELECT * FROM sessions
+            WHERE id = $1 AND revoked_at IS NULL AND expires_at > now()
+            "#,
+            id
+        )
+            .fetch_optional(&*self.pool)
+            .await
+    }
+
+    async fn update_tokens(
+        &self,
+        id: uuid::Uuid,
+        access_token: &[u8],
+        refresh_token: Option<&[u8]>,
+        id_token: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET access_token = $2,
+                refresh_token = COALESCE($3, refresh_token),
+                id_token = COALESCE($4, id_token),
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            id,
+            access_token,
+            refresh_token,
+            id_token
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke(&self, id: uuid::Uuid) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = now(), updated_at = now()
+            WHERE id = $1 AND revoked_at IS NULL
+            "#,
+            id
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_for_user(&self, user_id: uuid::Uuid) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = now(), updated_at = now()
+            WHERE user_id = $1 AND revoked_at IS NULL
+            "#,
+            user_id
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn revoke_by_idp_sid(&self, provider: &str, sid: &str) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET revoked_at = now(), updated_at = now()
+            WHERE provider = $1 AND idp_sid = $2 AND revoked_at IS NULL
+            "#,
+            provider,
+            sid
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_expired(&self) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE expires_at <= now() OR revoked_at IS NOT NULL
+            "#
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -2,7 +2,7 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use sqlx::{Error, PgPool};
 use uuid::Uuid;
-use crate::domain::entities::User;
+use crate::domain::entities::{User, UserProfile};
 use crate::shared::errors::service_error::ServiceError;
 use crate::domain::repositories::UserRepository as UserRepositoryTrait;
 
@@ -43,17 +43,27 @@ impl UserRepositoryTrait for PgUserRepo {
         issuer: &str,
         subject: &str,
         username: &str,
-        email: &str
+        email: &str,
+        profile: &UserProfile,
     ) -> Result<User, Error> {
         sqlx::query_as!(
             User,
             r#"
-            INSERT INTO users (id, idp_issuer, idp_subject, username, email)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO users (id, idp_issuer, idp_subject, username, email,
+                               name, given_name, family_name, picture, locale, email_verified, org_id, roles)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             ON CONFLICT (idp_issuer, idp_subject)
             DO UPDATE SET
                 username = EXCLUDED.username,
                 email = EXCLUDED.email,
+                name = COALESCE(EXCLUDED.name, users.name),
+                given_name = COALESCE(EXCLUDED.given_name, users.given_name),
+                family_name = COALESCE(EXCLUDED.family_name, users.family_name),
+                picture = COALESCE(EXCLUDED.picture, users.picture),
+                locale = COALESCE(EXCLUDED.locale, users.locale),
+                email_verified = COALESCE(EXCLUDED.email_verified, users.email_verified),
+                org_id = COALESCE(EXCLUDED.org_id, users.org_id),
+                roles = COALESCE(EXCLUDED.roles, users.roles),
                 updated_at = now()
             RETURNING *
             "#,
@@ -61,7 +71,15 @@ impl UserRepositoryTrait for PgUserRepo {
             issuer,
             subject,
             username,
-            email
+            email,
+            profile.name,
+            profile.given_name,
+            profile.family_name,
+            profile.picture,
+            profile.locale,
+            profile.email_verified,
+            profile.org_id,
+            profile.roles.as_deref()
         )
             .fetch_one(&*self.pool)
             .await
@@ -81,6 +99,21 @@ impl UserRepositoryTrait for PgUserRepo {
         Ok(())
     }
 
+    async fn update_roles(&self, id: uuid::Uuid, roles: &[String]) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET roles = $2, updated_at = now()
+            WHERE id = $1
+            "#,
+            id,
+            roles
+        )
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
     // Required by domain trait
     async fn find_by_id(&self, id: uuid::Uuid) -> Result<Option<User>, ServiceError> {
         sqlx::query_as!(
@@ -100,7 +133,7 @@ impl UserRepositoryTrait for PgUserRepo {
     }
 
     async fn save(&self, user: &User) -> Result<User, ServiceError> {
-        self.upsert_user(&user.idp_issuer, &user.idp_subject, &user.username, &user.email)
+        self.upsert_user(&user.idp_issuer, &user.idp_subject, &user.username, &user.email, &user.profile())
             .await
             .map_err(|e| ServiceError::Database(e.to_string()))
     }
@@ -38,6 +38,27 @@ pub fn oauth_state_cookie(state: String) -> Cookie<'static> {
         .build()
 }
 
+/// Nonce of the pending login, checked against the ID token's `nonce` claim.
+pub fn oauth_nonce_cookie(nonce: String) -> Cookie<'static> {
+    Cookie::build(("oauth_nonce", nonce))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(is_production()) // Only secure in production
+        .expires(OffsetDateTime::now_utc() + Duration::minutes(10))
+        .build()
+}
+
+pub fn oauth_return_to_cookie(target: String) -> Cookie<'static> {
+    Cookie::build(("oauth_return_to", target))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(is_production()) // Only secure in production
+        .expires(OffsetDateTime::now_utc() + Duration::minutes(10))
+        .build()
+}
+
 pub fn pkce_verifier_cookie(verifier: String) -> Cookie<'static> {
     Cookie::build(("pkce_verifier", verifier))
         .path("/")
@@ -48,6 +69,50 @@ pub fn pkce_verifier_cookie(verifier: String) -> Cookie<'static> {
         .build()
 }
 
+/// Provider chosen for an in-flight login (read back in the callback)
+pub fn oauth_provider_cookie(provider: String) -> Cookie<'static> {
+    Cookie::build(("oauth_provider", provider))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(is_production()) // Only secure in production
+        .expires(OffsetDateTime::now_utc() + Duration::minutes(10))
+        .build()
+}
+
+/// ID token kept as `id_token_hint` for RP-initiated logout
+pub fn id_token_cookie(token: String) -> Cookie<'static> {
+    Cookie::build(("id_token", token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(is_production()) // Only secure in production
+        .expires(OffsetDateTime::now_utc() + Duration::days(7)) // Same lifetime as the refresh token
+        .build()
+}
+
+/// Provider that issued the session's tokens, used to route refresh and revocation
+pub fn auth_provider_cookie(provider: String) -> Cookie<'static> {
+    Cookie::build(("auth_provider", provider))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(is_production()) // Only secure in production
+        .expires(OffsetDateTime::now_utc() + Duration::days(7)) // Same lifetime as the refresh token
+        .build()
+}
+
+/// Signed server-side session id (server session mode); replaces the token cookies
+pub fn session_cookie(value: String, ttl: Duration) -> Cookie<'static> {
+    Cookie::build(("session", value))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(is_production()) // Only secure in production
+        .expires(OffsetDateTime::now_utc() + ttl)
+        .build()
+}
+
 pub fn remove_cookie(name: &str) -> Cookie<'static> {
     Cookie::build((name.to_string(), ""))
         .path("/")
@@ -1,5 +1,7 @@
 use axum::http::StatusCode;
 use std::fmt::Debug;
+use crate::infrastructure::oidc::OidcError;
+use crate::shared::errors::ServiceError;
 
 pub fn auth_fail<E: Debug>(msg: &'static str) -> impl FnOnce(E) -> (StatusCode, String) {
     move |e| {
@@ -8,9 +10,31 @@ pub fn auth_fail<E: Debug>(msg: &'static str) -> impl FnOnce(E) -> (StatusCode,
     }
 }
 
+/// Like `auth_fail`, but an unreachable provider is a `503` rather than the caller's fault.
+pub fn oidc_fail(msg: &'static str) -> impl FnOnce(OidcError) -> (StatusCode, String) {
+    move |e| match e {
+        OidcError::Unavailable(_) => {
+            tracing::warn!(error=?e, "provider unavailable: {msg}");
+            (StatusCode::SERVICE_UNAVAILABLE, "identity provider unavailable".to_string())
+        }
+        e => auth_fail(msg)(e),
+    }
+}
+
 pub fn server_fail<E: Debug>(msg: &'static str) -> impl FnOnce(E) -> (StatusCode, String) {
     move |e| {
         tracing::error!(error=?e, "server error: {msg}");
         (StatusCode::INTERNAL_SERVER_ERROR, "internal server error".to_string())
     }
 }
+
+/// Map a `ServiceError` to a status code: client errors keep their message, the rest are logged.
+pub fn service_fail(msg: &'static str) -> impl FnOnce(ServiceError) -> (StatusCode, String) {
+    move |e| match e {
+        ServiceError::Validation(m) => (StatusCode::BAD_REQUEST, m),
+        ServiceError::NotFound(m) => (StatusCode::NOT_FOUND, m),
+        ServiceError::Authentication(m) => (StatusCode::UNAUTHORIZED, m),
+        ServiceError::Authorization(m) => (StatusCode::FORBIDDEN, m),
+        e => server_fail(msg)(e),
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde::Deserialize;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+use crate::app_state::AppState;
+use crate::application::api_key_service::API_KEY_AUDIENCE;
+use crate::application::dto::api_key::api_key_response::{ApiKeyResponse, CreatedApiKeyResponse};
+use crate::domain::entities::User;
+use crate::infrastructure::oidc::OidcClaims;
+use crate::shared::middleware::Claims;
+use crate::require_role;
+use crate::infrastructure::web::errors::{server_fail, service_fail};
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Roles the key may use; must be a subset of the caller's roles
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Key never expires when absent
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct RenameApiKeyRequest {
+    pub name: String,
+}
+
+/// Keys are managed by people signed in through OIDC, not by other keys or machines.
+async fn key_owner(state: &AppState, claims: &OidcClaims) -> Result<Arc<User>, (StatusCode, String)> {
+    require_role!(claims, "user");
+
+    if claims.aud == API_KEY_AUDIENCE || claims.is_machine() {
+        return Err((StatusCode::FORBIDDEN, "API keys can only be managed from an interactive login".into()));
+    }
+
+    state
+        .user_service
+        .get_user_by_identity(&claims.iss, &claims.sub)
+        .await
+        .map_err(server_fail("get_user_by_identity"))?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".into()))
+}
+
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    Claims(claims): Claims,
+) -> Result<Json<Vec<ApiKeyResponse>>, (StatusCode, String)> {
+    let user = key_owner(&state, &claims).await?;
+
+    let keys = state.api_key_service
+        .list(user.id)
+        .await
+        .map_err(service_fail("list api keys"))?;
+
+    Ok(Json(keys.into_iter().map(ApiKeyResponse::from).collect()))
+}
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Claims(claims): Claims,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedApiKeyResponse>), (StatusCode, String)> {
+    let user = key_owner(&state, &claims).await?;
+
+    let expires_at = request.expires_in_days
+        .map(|days| OffsetDateTime::now_utc() + Duration::days(days.into()));
+
+    let created = state.api_key_service
+        .create(user.id, &request.name, request.scopes, &claims.roles, expires_at)
+        .await
+        .map_err(service_fail("create api key"))?;
+
+    Ok((StatusCode::CREATED, Json(CreatedApiKeyResponse {
+        api_key: ApiKeyResponse::from(created.key),
+        key: created.secret,
+    })))
+}
+
+pub async fn rename_api_key(
+    State(state): State<AppState>,
+    Claims(claims): Claims,
+    Path(id): Path<Uuid>,
+    Json(request): Json<RenameApiKeyRequest>,
+) -> Result<Json<ApiKeyResponse>, (StatusCode, String)> {
+    let user = key_owner(&state, &claims).await?;
+
+    let key = state.api_key_service
+        .rename(user.id, id, &request.name)
+        .await
+        .map_err(service_fail("rename api key"))?;
+
+    Ok(Json(ApiKeyResponse::from(key)))
+}
+
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Claims(claims): Claims,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = key_owner(&state, &claims).await?;
+
+    state.api_key_service
+        .revoke(user.id, id)
+        .await
+        .map_err(service_fail("revoke api key"))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
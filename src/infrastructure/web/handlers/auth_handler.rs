@@ -1,5 +1,5 @@
 use axum::{Json};
-use axum::extract::{Query, State};
+use axum::extract::{Form, Query, State};
 use axum::response::{IntoResponse, Redirect};
 use axum_extra::extract::cookie::{CookieJar};
 use http::StatusCode;
@@ -11,9 +11,54 @@ use ring::rand::{SecureRandom, SystemRandom};
 use tracing::{debug, info};
 
 use crate::app_state::AppState;
-use crate::infrastructure::web::cookies::cookie_helper::{access_cookie, oauth_state_cookie, pkce_verifier_cookie, refresh_cookie, remove_cookie};
+use crate::application::dto::auth::authorization_request::AuthorizationRequest;
+use crate::application::dto::auth::device_authorization::{DeviceAuthorizationResponse, DeviceTokenPoll};
+use crate::application::dto::auth::token_response::TokenResponse;
+use crate::infrastructure::web::cookies::cookie_helper::{access_cookie, auth_provider_cookie, id_token_cookie, oauth_nonce_cookie, oauth_provider_cookie, oauth_return_to_cookie, oauth_state_cookie, pkce_verifier_cookie, refresh_cookie, remove_cookie, session_cookie};
 use crate::shared::middleware::Claims;
-use crate::infrastructure::web::errors::auth_fail;
+use crate::infrastructure::web::errors::{auth_fail, oidc_fail, server_fail, service_fail};
+use crate::infrastructure::oidc::{OidcError, TokenTypeHint};
+use crate::infrastructure::web::return_to::resolve_return_to;
+use crate::shared::errors::ServiceError;
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    /// Name of a configured provider; the default provider is used when absent
+    pub provider: Option<String>,
+    /// Step-up: `acr_values` and `max_age` from a `insufficient_user_authentication` error
+    pub acr_values: Option<String>,
+    pub max_age: Option<u64>,
+    /// `prompt`, e.g. `login` to force re-authentication or `select_account`
+    pub prompt: Option<String>,
+    /// Username or email to pre-fill on the provider's login page
+    pub login_hint: Option<String>,
+    /// Preferred login UI languages, space separated (`de-CH en`)
+    pub ui_locales: Option<String>,
+    /// Federated IdP to go to directly, e.g. the ZITADEL id of the Google IdP
+    pub idp_hint: Option<String>,
+    /// ZITADEL organization id to log in to
+    pub organization: Option<String>,
+    /// Deep link to land on after the callback; must match `RETURN_TO_ALLOWLIST`
+    pub return_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LogoutRequest {
+    /// Where to send the browser when the provider has no end-session endpoint
+    pub return_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeviceTokenRequest {
+    pub device_code: String,
+    /// Provider the device login was started with; the default provider when absent
+    pub provider: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BackchannelLogoutRequest {
+    pub logout_token: String,
+}
 
 #[derive(Deserialize)]
 pub struct AuthCallbackRequest {
@@ -53,16 +98,53 @@ fn generate_secure_state() -> String {
     random_b64url(48)
 }
 
-pub async fn login_handler(State(state): State<AppState>, jar: CookieJar) -> impl IntoResponse {
+pub async fn login_handler(
+    Query(query): Query<LoginRequest>,
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let provider = state.auth_service.providers
+        .get(query.provider.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        .name
+        .clone();
+
+    let options = AuthorizationRequest {
+        acr_values: query.acr_values,
+        max_age: query.max_age,
+        prompt: query.prompt,
+        login_hint: query.login_hint,
+        ui_locales: query.ui_locales,
+        idp_hint: query.idp_hint,
+        organization: query.organization,
+        ..Default::default()
+    };
+    options.validate().map_err(service_fail("invalid login options"))?;
+
+    let return_to = match query.return_to.as_deref() {
+        Some(raw) => Some(resolve_return_to(&state.config, raw).ok_or_else(|| {
+            tracing::warn!(return_to = %raw, "rejected login return_to outside the allowlist");
+            (StatusCode::BAD_REQUEST, "return_to not allowed".to_string())
+        })?),
+        None => None,
+    };
+
     // Clear any existing auth cookies
     let jar = jar
         .remove(remove_cookie("pkce_verifier"))
         .remove(remove_cookie("oauth_state"))
+        .remove(remove_cookie("oauth_nonce"))
+        .remove(remove_cookie("oauth_return_to"))
+        .remove(remove_cookie("oauth_provider"))
         .remove(remove_cookie("access_token"))
-        .remove(remove_cookie("refresh_token"));
+        .remove(remove_cookie("refresh_token"))
+        .remove(remove_cookie("auth_provider"))
+        .remove(remove_cookie("id_token"))
+        .remove(remove_cookie("session"));
 
     let (verifier, challenge) = generate_pkce_pair();
     let state_str = generate_secure_state();
+    let nonce = random_b64url(32);
 
     // Mask sensitive values if you *must* log
     debug!(state_len = state_str.len(), "generated oauth state");
@@ -70,14 +152,26 @@ pub async fn login_handler(State(state): State<AppState>, jar: CookieJar) -> imp
 
     let jar = jar
         .add(pkce_verifier_cookie(verifier.clone()))
-        .add(oauth_state_cookie(state_str.clone()));
+        .add(oauth_state_cookie(state_str.clone()))
+        .add(oauth_nonce_cookie(nonce.clone()))
+        .add(oauth_provider_cookie(provider.clone()));
+    let jar = match return_to {
+        Some(target) => jar.add(oauth_return_to_cookie(target.to_string())),
+        None => jar,
+    };
 
     let url = state.auth_service
-        .build_authorize_url(Some(&challenge), Some(state_str.clone()))
-        .await;
+        .build_authorize_url(Some(&provider), &AuthorizationRequest {
+            state: Some(state_str.clone()),
+            nonce: Some(nonce),
+            code_challenge: Some(challenge),
+            ..options
+        })
+        .await
+        .map_err(oidc_fail("build authorize url"))?;
 
-    debug!(%url, "redirecting to provider");
-    (jar, Redirect::to(&url))
+    debug!(%url, %provider, "redirecting to provider");
+    Ok((jar, Redirect::to(&url)))
 }
 
 pub async fn oauth_callback_handler(
@@ -118,26 +212,70 @@ pub async fn oauth_callback_handler(
         .value()
         .to_string();
 
-    debug!("exchanging code for token");
-    let token = state
+    let nonce = jar.get("oauth_nonce")
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "missing oauth_nonce cookie".to_string()))?
+        .value()
+        .to_string();
+
+    let provider = jar.get("oauth_provider")
+        .map(|c| c.value().to_string());
+
+    debug!(?provider, "exchanging code for token");
+    let authenticated = state
         .auth_service
-        .exchange_code_for_token(query.code, Some(verifier))
+        .exchange_code_for_token(provider.as_deref(), query.code, Some(verifier), Some(nonce))
         .await
-        .map_err(auth_fail("token exchange failed"))?;
+        .map_err(oidc_fail("token exchange failed"))?;
     info!("token exchange successful");
+    let token = authenticated.tokens;
+
+    // The cookie was checked at login; check again in case the allowlist changed since
+    let target = jar.get("oauth_return_to")
+        .and_then(|c| resolve_return_to(&state.config, c.value()))
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| state.config.frontend_url.clone());
 
     // Clear temp cookies, set real ones via helpers
     let mut jar = jar
         .remove(remove_cookie("pkce_verifier"))
-        .remove(remove_cookie("oauth_state"));
+        .remove(remove_cookie("oauth_state"))
+        .remove(remove_cookie("oauth_nonce"))
+        .remove(remove_cookie("oauth_return_to"))
+        .remove(remove_cookie("oauth_provider"));
+
+    // Server-side sessions: tokens stay on the server, the browser only gets the session id
+    if let Some(sessions) = &state.session_service {
+        let provider_name = state.auth_service.providers
+            .get(provider.as_deref())
+            .map_err(auth_fail("unknown provider"))?
+            .name
+            .clone();
+        let session = sessions
+            .create(authenticated.user.id, &provider_name, authenticated.claims.sid.as_deref(), &token)
+            .await
+            .map_err(server_fail("session create failed"))?;
+
+        let ttl = time::Duration::seconds(state.config.session_ttl_secs as i64);
+        let jar = jar
+            .remove(remove_cookie("access_token"))
+            .remove(remove_cookie("refresh_token"))
+            .remove(remove_cookie("auth_provider"))
+            .remove(remove_cookie("id_token"))
+            .add(session_cookie(session, ttl));
+
+        return Ok((jar, Redirect::to(&target)));
+    }
 
+    if let Some(p) = provider {
+        jar = jar.add(auth_provider_cookie(p));
+    }
     jar = jar.add(access_cookie(token.access_token.clone()));
     if let Some(rt) = token.refresh_token.clone() {
         jar = jar.add(refresh_cookie(rt));
     }
-
-    let target = std::env::var("FRONTEND_URL")
-        .unwrap_or_else(|_| "http://localhost:5173".to_string());
+    if let Some(idt) = token.id_token.clone() {
+        jar = jar.add(id_token_cookie(idt));
+    }
 
     Ok((jar, Redirect::to(&target)))
 }
@@ -146,38 +284,113 @@ pub async fn refresh_handler(
     State(state): State<AppState>,
     jar: CookieJar
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if let Some(sessions) = &state.session_service {
+        let session = match jar.get("session") {
+            Some(c) => sessions.resolve(c.value()).await.map_err(server_fail("session lookup failed"))?,
+            None => None,
+        };
+        let Some(session) = session else {
+            return Err((StatusCode::UNAUTHORIZED, "no session".into()));
+        };
+        let Some(refresh_token) = session.refresh_token.as_deref() else {
+            return Err((StatusCode::UNAUTHORIZED, "no refresh token".into()));
+        };
+
+        let token = state.auth_service
+            .refresh_access_token(Some(&session.provider), refresh_token)
+            .await;
+        if let Err(OidcError::RefreshTokenReuse | OidcError::RefreshTokenRevoked(_)) = &token
+            && let Err(e) = sessions.revoke(session.id).await
+        {
+            tracing::error!("Failed to revoke session after a refused refresh token: {:?}", e);
+        }
+        let token = token.map_err(oidc_fail("refresh failed"))?;
+        sessions.update_tokens(session.id, &token)
+            .await
+            .map_err(server_fail("session update failed"))?;
+
+        return Ok((jar, Json(serde_json::json!({"status": "refreshed"}))));
+    }
+
     let Some(refresh_cookie_value) = jar.get("refresh_token") else {
         return Err((StatusCode::UNAUTHORIZED, "no refresh token".into()));
     };
 
+    let provider = state.auth_service.providers
+        .get_explicit(jar.get("auth_provider").map(|c| c.value()))
+        .map_err(auth_fail("refresh provider"))?
+        .name
+        .clone();
+
     let token = state.auth_service
-        .refresh_access_token(refresh_cookie_value.value())
+        .refresh_access_token(Some(&provider), refresh_cookie_value.value())
         .await
-        .map_err(auth_fail("refresh failed"))?;
+        .map_err(oidc_fail("refresh failed"))?;
 
-    let mut jar = jar;
+    // Renew the provider cookie with the refresh token so it never outlives it
+    let mut jar = jar.add(auth_provider_cookie(provider));
     jar = jar.add(access_cookie(token.access_token.clone()));
     if let Some(rt) = token.refresh_token.clone() {
         jar = jar.add(refresh_cookie(rt));
     }
+    if let Some(idt) = token.id_token.clone() {
+        jar = jar.add(id_token_cookie(idt));
+    }
 
     Ok((jar, Json(serde_json::json!({"status": "refreshed"}))))
 }
 
 pub async fn logout_handler(
+    Query(query): Query<LogoutRequest>,
     State(state): State<AppState>,
     jar: CookieJar
 ) -> impl IntoResponse {
+    let return_to = query.return_to.as_deref().and_then(|raw| {
+        let target = resolve_return_to(&state.config, raw);
+        if target.is_none() {
+            tracing::warn!(return_to = %raw, "ignored logout return_to outside the allowlist");
+        }
+        target
+    });
+
+    let mut provider = jar.get("auth_provider").map(|c| c.value().to_string());
+    let mut id_token_hint = jar.get("id_token").map(|c| c.value().to_string());
+
+    // Server-side session: revoke the stored tokens, then the session itself
+    if let Some(sessions) = &state.session_service
+        && let Some(cookie) = jar.get("session")
+    {
+        match sessions.resolve(cookie.value()).await {
+            Ok(Some(session)) => {
+                if let Some(rt) = &session.refresh_token
+                    && let Err(e) = state.auth_service.revoke_token(Some(&session.provider), rt, Some(TokenTypeHint::RefreshToken)).await
+                {
+                    tracing::warn!("Failed to revoke refresh token at provider: {}", e);
+                }
+                if let Err(e) = state.auth_service.revoke_token(Some(&session.provider), &session.access_token, Some(TokenTypeHint::AccessToken)).await {
+                    tracing::warn!("Failed to revoke access token at provider: {}", e);
+                }
+                if let Err(e) = sessions.revoke(session.id).await {
+                    tracing::error!("Failed to revoke session: {:?}", e);
+                }
+                provider = Some(session.provider);
+                id_token_hint = session.id_token;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to load session on logout: {:?}", e),
+        }
+    }
+
     // Attempt to revoke tokens at the provider before clearing local cookies
     if let Some(refresh_token_cookie) = jar.get("refresh_token") {
-        if let Err(e) = state.auth_service.revoke_token(refresh_token_cookie.value()).await {
+        if let Err(e) = state.auth_service.revoke_token(provider.as_deref(), refresh_token_cookie.value(), Some(TokenTypeHint::RefreshToken)).await {
             tracing::warn!("Failed to revoke refresh token at provider: {}", e);
             // Continue with logout even if revocation fails
         }
     }
 
     if let Some(access_token_cookie) = jar.get("access_token") {
-        if let Err(e) = state.auth_service.revoke_token(access_token_cookie.value()).await {
+        if let Err(e) = state.auth_service.revoke_token(provider.as_deref(), access_token_cookie.value(), Some(TokenTypeHint::AccessToken)).await {
             tracing::warn!("Failed to revoke access token at provider: {}", e);
             // Continue with logout even if revocation fails
         }
@@ -187,16 +400,142 @@ pub async fn logout_handler(
     let jar = jar
         .remove(remove_cookie("access_token"))
         .remove(remove_cookie("refresh_token"))
+        .remove(remove_cookie("auth_provider"))
+        .remove(remove_cookie("id_token"))
+        .remove(remove_cookie("session"))
         .remove(remove_cookie("pkce_verifier"))
-        .remove(remove_cookie("oauth_state"));
+        .remove(remove_cookie("oauth_state"))
+        .remove(remove_cookie("oauth_nonce"))
+        .remove(remove_cookie("oauth_return_to"))
+        .remove(remove_cookie("oauth_provider"));
 
     info!("User logged out successfully");
 
-    let target = std::env::var("FRONTEND_URL")
-        .unwrap_or_else(|_| "http://localhost:5173".to_string());
+    // RP-initiated logout: end the provider's SSO session too, so the next login isn't silent
+    let end_session = state.auth_service
+        .end_session_url(provider.as_deref(), id_token_hint.as_deref())
+        .unwrap_or_else(|e| {
+            tracing::warn!("Cannot build end-session URL: {}", e);
+            None
+        });
+
+    // The provider sends the browser to its registered post-logout URI, so return_to only
+    // applies when there is no end-session endpoint
+    let target = end_session
+        .or_else(|| return_to.map(|url| url.to_string()))
+        .unwrap_or_else(|| state.config.frontend_url.clone());
     (jar, Redirect::to(&target))
 }
 
+/// Start a device login (RFC 8628). The device shows `user_code` and
+/// `verification_uri` to the user, then polls `/api/auth/device/token`.
+pub async fn device_login_handler(
+    Query(query): Query<LoginRequest>,
+    State(state): State<AppState>,
+) -> Result<Json<DeviceAuthorizationResponse>, (StatusCode, String)> {
+    let response = state.auth_service
+        .start_device_login(query.provider.as_deref())
+        .await
+        .map_err(|e| match e {
+            OidcError::UnknownProvider(_) | OidcError::NotImplemented(_) => (StatusCode::BAD_REQUEST, e.to_string()),
+            e @ OidcError::Unavailable(_) => oidc_fail("device authorization failed")(e),
+            e => server_fail("device authorization failed")(e),
+        })?;
+
+    debug!(expires_in = response.expires_in, "device login started");
+    Ok(Json(response))
+}
+
+/// Poll a device login. Answers like an RFC 8628 token endpoint, so standard
+/// device-flow clients can poll it: tokens once approved, otherwise a 400 with
+/// `authorization_pending`, `slow_down`, `access_denied` or `expired_token`.
+pub async fn device_token_handler(
+    State(state): State<AppState>,
+    Json(request): Json<DeviceTokenRequest>,
+) -> Result<Json<TokenResponse>, (StatusCode, Json<Value>)> {
+    let error = |code: &str| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": code })));
+
+    // Only the provider's verdict on the device code is terminal; anything else leaves the
+    // client polling
+    let poll = state.auth_service
+        .poll_device_login(request.provider.as_deref(), &request.device_code)
+        .await
+        .map_err(|e| match e {
+            OidcError::UnknownProvider(_) => error("invalid_request"),
+            OidcError::Unavailable(_) | OidcError::Network(_) => {
+                tracing::warn!(error = ?e, "device token poll failed, provider unavailable");
+                (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "error": "temporarily_unavailable" })))
+            }
+            e => {
+                tracing::error!(error = ?e, "device token poll failed");
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": "server_error" })))
+            }
+        })?;
+
+    match poll {
+        DeviceTokenPoll::Pending => Err(error("authorization_pending")),
+        DeviceTokenPoll::SlowDown => Err(error("slow_down")),
+        DeviceTokenPoll::Denied => Err(error("access_denied")),
+        DeviceTokenPoll::Expired => Err(error("expired_token")),
+        DeviceTokenPoll::InvalidGrant => Err(error("invalid_grant")),
+        DeviceTokenPoll::Complete(authenticated) => {
+            info!("device login successful");
+            Ok(Json(authenticated.tokens))
+        }
+    }
+}
+
+/// OpenID Connect Back-Channel Logout receiver. The provider posts a signed logout
+/// token when a user's session ends there; we end the matching local logins.
+pub async fn backchannel_logout_handler(
+    State(state): State<AppState>,
+    Form(request): Form<BackchannelLogoutRequest>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        tracing::warn!(error = %e, "rejected back-channel logout token");
+        (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "invalid_request",
+            "error_description": "invalid logout token",
+        })))
+    };
+
+    let registered = state.auth_service.providers
+        .for_token(&request.logout_token)
+        .map_err(|e| invalid(&e))?;
+    let logout = registered.provider
+        .validate_logout_token(&request.logout_token)
+        .await
+        .map_err(|e| invalid(&e))?;
+
+    state.logout_service
+        .backchannel_logout(&registered.name, &logout)
+        .await
+        .map_err(|e| match e {
+            ServiceError::Validation(m) => invalid(&m),
+            e => {
+                tracing::error!(error = ?e, "back-channel logout failed");
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": "server_error" })))
+            }
+        })?;
+
+    info!(provider = %registered.name, sub = ?logout.sub, sid = ?logout.sid, "back-channel logout processed");
+    Ok(StatusCode::OK)
+}
+
+/// List the configured providers so the frontend can offer a choice at login.
+pub async fn providers_handler(State(state): State<AppState>) -> Json<Value> {
+    let providers: Vec<Value> = state.auth_service.providers
+        .all()
+        .iter()
+        .map(|p| serde_json::json!({
+            "name": p.name,
+            "issuer": p.provider.issuer(),
+            "available": p.provider.health().available,
+        }))
+        .collect();
+    Json(serde_json::json!({ "providers": providers }))
+}
+
 pub async fn me_handler(Claims(claims): Claims) -> Json<Value> {
     Json(serde_json::json!(claims))
 }
\ No newline at end of file
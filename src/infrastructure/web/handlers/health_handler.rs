@@ -0,0 +1,28 @@
+use axum::Json;
+use axum::extract::State;
+use serde_json::Value;
+use crate::app_state::AppState;
+
+/// Liveness plus provider health. Always `200` so the API isn't restarted while an issuer is
+/// down; `status` is `degraded` when any provider has no metadata or its last refresh failed.
+pub async fn health_handler(State(state): State<AppState>) -> Json<Value> {
+    let mut degraded = false;
+    let providers: Vec<Value> = state.auth_service.providers
+        .all()
+        .iter()
+        .map(|p| {
+            let health = p.provider.health();
+            degraded |= !health.available || health.last_error.is_some();
+            serde_json::json!({
+                "name": p.name,
+                "issuer": p.provider.issuer(),
+                "health": health,
+            })
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "status": if degraded { "degraded" } else { "ok" },
+        "providers": providers,
+    }))
+}
@@ -1,2 +1,5 @@
 pub mod user_handler;
-pub mod auth_handler;
\ No newline at end of file
+pub mod auth_handler;
+pub mod api_key_handler;
+pub mod health_handler;
+pub mod service_token_handler;
\ No newline at end of file
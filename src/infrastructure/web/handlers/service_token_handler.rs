@@ -0,0 +1,31 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde_json::Value;
+use crate::app_state::AppState;
+use crate::infrastructure::oidc::OidcError;
+use crate::infrastructure::web::errors::oidc_fail;
+use crate::shared::middleware::{AccessToken, Claims};
+
+/// A token for the downstream service `service` carrying the caller's identity (RFC 8693),
+/// exchanged for the access token the request was authenticated with.
+pub async fn service_token_handler(
+    State(state): State<AppState>,
+    Path(service): Path<String>,
+    Claims(claims): Claims,
+    AccessToken(subject_token): AccessToken,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let token = state.auth_service
+        .exchange_for_service(&claims, &subject_token, &service)
+        .await
+        .map_err(|e| match e {
+            OidcError::UnknownService(name) => (StatusCode::NOT_FOUND, format!("unknown service: {name}")),
+            e => oidc_fail("token exchange failed")(e),
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "access_token": token.access_token,
+        "token_type": token.token_type.unwrap_or_else(|| "Bearer".to_string()),
+        "expires_in": token.expires_in,
+    })))
+}
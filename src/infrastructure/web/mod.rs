@@ -3,3 +3,4 @@ pub mod routes;
 pub mod errors;
 pub mod client;
 pub mod cookies;
+pub mod return_to;
@@ -3,7 +3,7 @@ use http::header::{CACHE_CONTROL, PRAGMA};
 use http::HeaderValue;
 use tower_http::set_header::SetResponseHeaderLayer;
 use crate::app_state::AppState;
-use crate::infrastructure::web::handlers::auth_handler::{login_handler, logout_handler, me_handler, oauth_callback_handler, refresh_handler};
+use crate::infrastructure::web::handlers::auth_handler::{backchannel_logout_handler, device_login_handler, device_token_handler, login_handler, logout_handler, me_handler, oauth_callback_handler, providers_handler, refresh_handler};
 
 pub fn auth_routes() -> Router<AppState> {
         Router::new()
@@ -12,6 +12,10 @@ pub fn auth_routes() -> Router<AppState> {
             .route("/logout",   post(logout_handler))
             .route("/refresh",  get(refresh_handler))
             .route("/callback", get(oauth_callback_handler))
+            .route("/providers", get(providers_handler))
+            .route("/backchannel-logout", post(backchannel_logout_handler))
+            .route("/device", post(device_login_handler))
+            .route("/device/token", post(device_token_handler))
             .route_layer(SetResponseHeaderLayer::if_not_present(
                 CACHE_CONTROL,
                 HeaderValue::from_static("no-store"),
@@ -1,8 +1,13 @@
-use axum::{Router, routing::get};
+use axum::{Router, routing::{get, patch}};
 use crate::app_state::AppState;
+use crate::infrastructure::web::handlers::api_key_handler::{create_api_key, list_api_keys, rename_api_key, revoke_api_key};
+use crate::infrastructure::web::handlers::service_token_handler::service_token_handler;
 use crate::infrastructure::web::handlers::user_handler::get_user_info;
 
 pub fn user_routes() -> Router<AppState> {
     Router::new()
         .route("/me", get(get_user_info))
+        .route("/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/api-keys/{id}", patch(rename_api_key).delete(revoke_api_key))
+        .route("/services/{service}/token", get(service_token_handler))
 }
@@ -0,0 +1,65 @@
+use axum::{
+    extract::OriginalUri,
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::app_state::AppState;
+use crate::infrastructure::oidc::dpop::DPOP_ALGORITHMS;
+use crate::infrastructure::oidc::OidcClaims;
+
+use super::tokens::validate_token;
+
+/// 401 with a `WWW-Authenticate: DPoP` challenge (RFC 9449 §7.1).
+pub fn dpop_reject(error: &str, description: &str) -> Response {
+    let algs = DPOP_ALGORITHMS.iter()
+        .map(|a| format!("{a:?}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let challenge = format!(r#"DPoP error="{error}", error_description="{description}", algs="{algs}""#);
+
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, challenge)], description.to_string()).into_response()
+}
+
+/// Authenticate `Authorization: DPoP <token>`: the request must carry exactly one valid
+/// proof, and the token must be bound (`cnf.jkt`) to the proof's key.
+pub async fn authenticate_dpop(app: &AppState, parts: &Parts, token: &str) -> Result<OidcClaims, Response> {
+    let mut proofs = parts.headers.get_all("DPoP").iter();
+    let (Some(proof), None) = (proofs.next(), proofs.next()) else {
+        return Err(dpop_reject("invalid_dpop_proof", "exactly one DPoP proof required"));
+    };
+    let proof = proof.to_str()
+        .map_err(|_| dpop_reject("invalid_dpop_proof", "malformed DPoP header"))?;
+
+    let Some(htu) = request_htu(app, parts) else {
+        tracing::warn!("DPoP token presented but PUBLIC_URL is not set");
+        return Err(dpop_reject("invalid_token", "DPoP requires PUBLIC_URL on the server"));
+    };
+
+    let jkt = app.dpop_verifier
+        .verify(proof, parts.method.as_str(), &htu, token)
+        .await
+        .map_err(|e| {
+            tracing::debug!(error = %e, "DPoP proof rejected");
+            dpop_reject("invalid_dpop_proof", "invalid DPoP proof")
+        })?;
+
+    let claims = validate_token(app, token).await?;
+
+    if claims.cnf_jkt.as_deref() != Some(jkt.as_str()) {
+        return Err(dpop_reject("invalid_token", "token is not bound to the DPoP key"));
+    }
+
+    Ok(claims)
+}
+
+/// The request URI as the client sees it: `PUBLIC_URL` plus the full path before any router
+/// nesting. `Host` and `X-Forwarded-*` are chosen by the caller, so they can't be what the
+/// proof is checked against; without `PUBLIC_URL` there is no `htu` to check.
+fn request_htu(app: &AppState, parts: &Parts) -> Option<String> {
+    let base = app.config.public_url.as_deref()?;
+    let path = parts.extensions.get::<OriginalUri>()
+        .map(|uri| uri.path())
+        .unwrap_or_else(|| parts.uri.path());
+    Some(format!("{base}{path}"))
+}
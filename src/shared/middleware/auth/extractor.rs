@@ -7,11 +7,20 @@ use std::future::Future;
 
 use crate::app_state::AppState;
 use crate::infrastructure::oidc::OidcClaims;
+use crate::shared::errors::ServiceError;
 
+use super::dpop::{authenticate_dpop, dpop_reject};
+use super::session::authenticate_session;
 use super::tokens::{extract_token_from_request, validate_token, attempt_token_refresh, TokenSource};
 
 pub struct Claims(pub OidcClaims);
 
+/// The access token the request's `Claims` were validated from, for calls made on the
+/// user's behalf (token exchange). Cookie, session and plain bearer requests have one;
+/// API keys and DPoP-bound tokens, which can't be passed on, don't.
+#[derive(Clone)]
+pub struct AccessToken(pub String);
+
 impl<S> FromRequestParts<S> for Claims
 where
     S: Send + Sync + 'static,
@@ -29,13 +38,47 @@ where
             // Try to extract and validate existing token
             match extract_token_from_request(parts, state).await {
                 Ok(token_source) => {
-                    let (token, is_cookie) = match &token_source {
-                        TokenSource::Bearer(t) => (t.clone(), false),
-                        TokenSource::Cookie(t) => (t.clone(), true),
+                    let (token, is_cookie) = match token_source {
+                        TokenSource::Bearer(t) => (t, false),
+                        TokenSource::Dpop(t) => {
+                            // Never refreshed: the client holds the DPoP key and refreshes itself
+                            return authenticate_dpop(&app, parts, &t).await.map(Self);
+                        }
+                        TokenSource::Cookie(t) => (t, true),
+                        TokenSource::ApiKey(key) => {
+                            // API keys are never refreshed; claims are limited to the key's scopes
+                            return app.api_key_service
+                                .authenticate(&key)
+                                .await
+                                .map(Self)
+                                .map_err(|e| match e {
+                                    ServiceError::Authentication(msg) => (StatusCode::UNAUTHORIZED, msg).into_response(),
+                                    e => {
+                                        tracing::error!(error = ?e, "API key authentication failed");
+                                        (StatusCode::INTERNAL_SERVER_ERROR, "internal server error").into_response()
+                                    }
+                                });
+                        }
+                        TokenSource::Session(value) => {
+                            // Server-side session: refresh happens against the stored tokens
+                            let Some(sessions) = &app.session_service else {
+                                return Err((StatusCode::UNAUTHORIZED, "Sessions not enabled").into_response());
+                            };
+                            let (claims, token) = authenticate_session(&app, sessions, &value).await?;
+                            parts.extensions.insert(AccessToken(token));
+                            return Ok(Self(claims));
+                        }
                     };
 
                     match validate_token(&app, &token).await {
-                        Ok(claims) => return Ok(Self(claims)),
+                        // A DPoP-bound token used as a plain bearer token was probably stolen
+                        Ok(claims) if !is_cookie && claims.cnf_jkt.is_some() => {
+                            return Err(dpop_reject("invalid_token", "DPoP-bound token requires a DPoP proof"));
+                        }
+                        Ok(claims) => {
+                            parts.extensions.insert(AccessToken(token));
+                            return Ok(Self(claims));
+                        }
                         Err(_) => {
                             // Token validation failed, try refresh if it was a cookie token
                             if is_cookie {
@@ -54,6 +97,9 @@ where
             // Attempt token refresh
             match attempt_token_refresh(parts, state).await {
                 Ok((claims, new_jar)) => {
+                    if let Some(token) = new_jar.get("access_token") {
+                        parts.extensions.insert(AccessToken(token.value().to_string()));
+                    }
                     // Store the new jar in request extensions for later propagation
                     parts.extensions.insert(new_jar);
                     Ok(Self(claims))
@@ -62,4 +108,21 @@ where
             }
         }
     }
-}
\ No newline at end of file
+}
+
+impl<S> FromRequestParts<S> for AccessToken
+where
+    S: Send + Sync + 'static,
+    AppState: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if parts.extensions.get::<AccessToken>().is_none() {
+            Claims::from_request_parts(parts, state).await?;
+        }
+        parts.extensions.get::<AccessToken>()
+            .cloned()
+            .ok_or_else(|| (StatusCode::FORBIDDEN, "This credential can't be used on the user's behalf").into_response())
+    }
+}
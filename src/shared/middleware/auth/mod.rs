@@ -1,2 +1,5 @@
 pub mod extractor;
-pub mod tokens;
\ No newline at end of file
+pub mod session;
+pub mod tokens;
+pub mod step_up;
+pub mod dpop;
@@ -0,0 +1,62 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::app_state::AppState;
+use crate::application::session_service::SessionService;
+use crate::infrastructure::oidc::{OidcClaims, OidcError};
+
+use super::tokens::validate_token;
+
+/// Authenticate a request by its server-side session.
+///
+/// The stored access token is validated first; when it is no longer valid the
+/// stored refresh token is used and the session updated in place. The session
+/// cookie itself never changes, so nothing has to be propagated to the response.
+/// Returns the claims together with the access token they were validated from.
+pub async fn authenticate_session(
+    app: &AppState,
+    sessions: &SessionService,
+    cookie_value: &str,
+) -> Result<(OidcClaims, String), Response> {
+    let session = sessions
+        .resolve(cookie_value)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = ?e, "failed to load session");
+            (StatusCode::UNAUTHORIZED, "Invalid session").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Session expired or revoked").into_response())?;
+
+    if let Ok(claims) = validate_token(app, &session.access_token).await {
+        return Ok((claims, session.access_token));
+    }
+
+    let refresh_token = session.refresh_token.as_deref()
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Session expired").into_response())?;
+
+    let token_pair = app
+        .auth_service
+        .refresh_access_token(Some(&session.provider), refresh_token)
+        .await;
+    if let Err(OidcError::RefreshTokenReuse | OidcError::RefreshTokenRevoked(_)) = &token_pair
+        && let Err(e) = sessions.revoke(session.id).await
+    {
+        tracing::error!(error = ?e, "failed to revoke session after a refused refresh token");
+    }
+    let token_pair = token_pair
+        .map_err(|e| {
+            (StatusCode::UNAUTHORIZED, format!("Token refresh failed: {e}"))
+                .into_response()
+        })?;
+
+    let claims = validate_token(app, &token_pair.access_token).await?;
+
+    sessions.update_tokens(session.id, &token_pair).await.map_err(|e| {
+        tracing::error!(error = ?e, "failed to store refreshed session tokens");
+        (StatusCode::INTERNAL_SERVER_ERROR, "internal server error").into_response()
+    })?;
+
+    Ok((claims, token_pair.access_token))
+}
@@ -0,0 +1,189 @@
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use std::marker::PhantomData;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app_state::AppState;
+use crate::infrastructure::oidc::OidcClaims;
+
+use super::extractor::Claims;
+use super::tokens::{extract_token_from_request, TokenSource};
+
+/// RFC 9470 error code for a token whose authentication is too weak or too old.
+pub const INSUFFICIENT_USER_AUTHENTICATION: &str = "insufficient_user_authentication";
+
+/// How strongly, and how recently, the user must have authenticated.
+#[derive(Debug, Clone, Copy)]
+pub struct StepUpRequirement {
+    /// Accepted `acr` values, in order of preference; they are requested on step-up
+    pub acr_values: &'static [&'static str],
+    /// Accepted `amr` methods; any one of them satisfies the level as well
+    pub amr: &'static [&'static str],
+    /// Maximum seconds since `auth_time`
+    pub max_age: Option<u64>,
+}
+
+impl StepUpRequirement {
+    pub fn check(&self, claims: &OidcClaims) -> Result<(), StepUpRequired> {
+        let level_ok = (self.acr_values.is_empty() && self.amr.is_empty())
+            || claims.acr.as_deref().is_some_and(|acr| self.acr_values.contains(&acr))
+            || claims.amr.iter().any(|m| self.amr.contains(&m.as_str()));
+
+        let age_ok = match self.max_age {
+            None => true,
+            Some(max_age) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                claims.auth_time.is_some_and(|t| now.saturating_sub(t) <= max_age)
+            }
+        };
+
+        if level_ok && age_ok {
+            return Ok(());
+        }
+
+        tracing::debug!(sub = %claims.sub, acr = ?claims.acr, amr = ?claims.amr, level_ok, age_ok, "step-up authentication required");
+        Err(StepUpRequired {
+            acr_values: (!self.acr_values.is_empty()).then(|| self.acr_values.join(" ")),
+            max_age: self.max_age,
+        })
+    }
+}
+
+/// Rejection telling the client to log in again with `acr_values` / `max_age`
+/// (passed through by `/api/auth/login`).
+#[derive(Debug)]
+pub struct StepUpRequired {
+    pub acr_values: Option<String>,
+    pub max_age: Option<u64>,
+}
+
+impl IntoResponse for StepUpRequired {
+    fn into_response(self) -> Response {
+        let description = "A stronger or more recent authentication is required";
+
+        let mut challenge = format!(
+            r#"Bearer error="{INSUFFICIENT_USER_AUTHENTICATION}", error_description="{description}""#
+        );
+        if let Some(acr) = &self.acr_values {
+            challenge.push_str(&format!(r#", acr_values="{acr}""#));
+        }
+        if let Some(max_age) = self.max_age {
+            challenge.push_str(&format!(", max_age={max_age}"));
+        }
+
+        let body = serde_json::json!({
+            "error": INSUFFICIENT_USER_AUTHENTICATION,
+            "error_description": description,
+            "acr_values": self.acr_values,
+            "max_age": self.max_age,
+        });
+
+        (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, challenge)], Json(body)).into_response()
+    }
+}
+
+/// A named step-up requirement, used as the type parameter of `StepUp`.
+pub trait StepUpPolicy: Send + Sync + 'static {
+    const REQUIREMENT: StepUpRequirement;
+}
+
+/// Multi-factor login (`amr` contains `mfa`), at any time.
+pub struct Mfa;
+
+impl StepUpPolicy for Mfa {
+    const REQUIREMENT: StepUpRequirement = StepUpRequirement {
+        acr_values: &[],
+        amr: &["mfa"],
+        max_age: None,
+    };
+}
+
+/// Multi-factor login within the last 5 minutes, for destructive or admin actions.
+pub struct RecentMfa;
+
+impl StepUpPolicy for RecentMfa {
+    const REQUIREMENT: StepUpRequirement = StepUpRequirement {
+        acr_values: &[],
+        amr: &["mfa"],
+        max_age: Some(5 * 60),
+    };
+}
+
+/// `Claims` that also satisfy the step-up policy `P`, e.g. `StepUp<RecentMfa>`.
+pub struct StepUp<P: StepUpPolicy>(pub OidcClaims, PhantomData<P>);
+
+impl<S, P> FromRequestParts<S> for StepUp<P>
+where
+    S: Send + Sync + 'static,
+    AppState: FromRef<S>,
+    P: StepUpPolicy,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Claims(mut claims) = Claims::from_request_parts(parts, state).await?;
+
+        // Access tokens rarely carry the authentication context; the ID token of the same
+        // login, kept in a cookie or the server-side session, does
+        if P::REQUIREMENT.check(&claims).is_err() {
+            let app = AppState::from_ref(state);
+            if let Some(id_token) = stored_id_token(&app, parts, state).await {
+                merge_id_token_context(&app, &mut claims, &id_token).await;
+            }
+        }
+
+        P::REQUIREMENT.check(&claims).map_err(IntoResponse::into_response)?;
+        Ok(Self(claims, PhantomData))
+    }
+}
+
+/// The ID token stored with the login the request was authenticated by: the cookie (renewed
+/// by a refresh in this request, if any) or the server-side session. Bearer, DPoP and API key
+/// requests have none.
+async fn stored_id_token<S>(app: &AppState, parts: &mut Parts, state: &S) -> Option<String>
+where
+    S: Send + Sync + 'static,
+    AppState: FromRef<S>,
+{
+    if let Some(jar) = parts.extensions.get::<CookieJar>() {
+        return jar.get("id_token").map(|c| c.value().to_string());
+    }
+
+    match extract_token_from_request(parts, state).await.ok()? {
+        TokenSource::Cookie(_) => {
+            let jar = CookieJar::from_request_parts(parts, state).await.ok()?;
+            jar.get("id_token").map(|c| c.value().to_string())
+        }
+        TokenSource::Session(value) => {
+            let session = app.session_service.as_ref()?.resolve(&value).await.ok()??;
+            session.id_token
+        }
+        TokenSource::Bearer(_) | TokenSource::Dpop(_) | TokenSource::ApiKey(_) => None,
+    }
+}
+
+/// Validate `id_token` with the provider that issued `claims` and take the authentication
+/// context from it, if it belongs to the same subject (and provider session, when both carry
+/// one). An expired ID token adds nothing.
+async fn merge_id_token_context(app: &AppState, claims: &mut OidcClaims, id_token: &str) {
+    let Some(registered) = app.auth_service.providers.by_issuer(&claims.iss) else {
+        return;
+    };
+    match registered.provider.validate_id_token(id_token).await {
+        Ok(id_claims) if id_claims.sub == claims.sub
+            && (claims.sid.is_none() || id_claims.sid.is_none() || claims.sid == id_claims.sid) =>
+        {
+            claims.merge_authentication_context(&id_claims)
+        }
+        Ok(_) => tracing::warn!(sub = %claims.sub, "stored ID token belongs to another login, ignoring it"),
+        Err(e) => tracing::debug!(sub = %claims.sub, error = %e, "stored ID token not usable for step-up"),
+    }
+}
@@ -11,13 +11,20 @@ use axum_extra::{
 
 use crate::app_state::AppState;
 use crate::infrastructure::oidc::OidcClaims;
+use crate::infrastructure::web::cookies::cookie_helper::auth_provider_cookie;
 
 pub enum TokenSource {
     Bearer(String),
+    /// Sender-constrained token (`Authorization: DPoP <token>`), sent with a `DPoP` proof
+    Dpop(String),
     Cookie(String),
+    /// Signed server-side session id (server session mode only)
+    Session(String),
+    /// Personal API key (`Authorization: ApiKey <key>`)
+    ApiKey(String),
 }
 
-/// Extract token from Authorization header or access_token cookie
+/// Extract token from Authorization header, session cookie or access_token cookie
 pub async fn extract_token_from_request<S>(
     parts: &mut Parts,
     state: &S,
@@ -26,18 +33,42 @@ where
     S: Send + Sync + 'static,
     AppState: FromRef<S>,
 {
-    // 1) Try Authorization: Bearer <token>
+    // 0) Try Authorization: ApiKey <key>
+    if let Some(key) = parts.headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("ApiKey "))
+    {
+        return Ok(TokenSource::ApiKey(key.trim().to_string()));
+    }
+
+    // 1) Try Authorization: DPoP <token>
+    if let Some(token) = parts.headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("DPoP "))
+    {
+        return Ok(TokenSource::Dpop(token.trim().to_string()));
+    }
+
+    // 2) Try Authorization: Bearer <token>
     if let Ok(TypedHeader(Authorization(bearer))) =
         TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state).await
     {
         return Ok(TokenSource::Bearer(bearer.token().to_string()));
     }
 
-    // 2) Try Cookie fallback
+    // 3) Try Cookie fallback
     let jar = CookieJar::from_request_parts(parts, state)
         .await
         .map_err(|_| (StatusCode::UNAUTHORIZED, "Missing CookieJar").into_response())?;
 
+    if AppState::from_ref(state).session_service.is_some()
+        && let Some(c) = jar.get("session")
+    {
+        return Ok(TokenSource::Session(c.value().to_string()));
+    }
+
     if let Some(c) = jar.get("access_token") {
         return Ok(TokenSource::Cookie(c.value().to_string()));
     }
@@ -56,19 +87,26 @@ pub async fn validate_token(
         .await
         .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()).into_response())?;
 
-    // Additional expiration check (defense in depth)
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    // Expiry, maximum age, audiences and required claims are enforced by the
+    // issuer's `ValidationPolicy` during validation
 
-    if claims.exp <= now {
-        return Err((StatusCode::UNAUTHORIZED, "Token expired").into_response());
+    // Reject tokens of logins ended by a back-channel logout
+    let logged_out = app_state
+        .logout_service
+        .is_logged_out(&claims.iss, &claims.sub, claims.sid.as_deref(), claims.iat)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())?;
+    if logged_out {
+        return Err((StatusCode::UNAUTHORIZED, "Session logged out").into_response());
     }
 
-    // Check if token is too old (issued more than reasonable time ago)
-    if now.saturating_sub(claims.iat) > 86400 { // 24 hours
-        return Err((StatusCode::UNAUTHORIZED, "Token too old").into_response());
+    // Machine principals have no user record; keep their agent identity up to date instead
+    if claims.is_machine() {
+        app_state
+            .agent_service
+            .sync_agent_from_claims(&claims)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())?;
     }
 
     Ok(claims)
@@ -92,9 +130,15 @@ where
     let refresh_token = jar.get("refresh_token")
         .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Missing refresh token").into_response())?;
 
+    let provider = app.auth_service.providers
+        .get_explicit(jar.get("auth_provider").map(|c| c.value()))
+        .map_err(|e| (StatusCode::UNAUTHORIZED, format!("Token refresh failed: {e}")).into_response())?
+        .name
+        .clone();
+
     let token_pair = app
         .auth_service
-        .refresh_access_token(refresh_token.value())
+        .refresh_access_token(Some(&provider), refresh_token.value())
         .await
         .map_err(|e| {
             (StatusCode::UNAUTHORIZED, format!("Token refresh failed: {e}"))
@@ -127,7 +171,19 @@ where
         .secure(is_production()) // Only secure in production
         .build();
 
-    let new_jar = jar.clone().add(access_cookie).add(refresh_cookie);
+    let mut new_jar = jar.clone()
+        .add(access_cookie)
+        .add(refresh_cookie)
+        .add(auth_provider_cookie(provider));
+
+    if let Some(id_token) = token_pair.id_token.clone() {
+        let id_token_cookie = Cookie::build(("id_token", id_token))
+            .http_only(true)
+            .path("/")
+            .secure(is_production()) // Only secure in production
+            .build();
+        new_jar = new_jar.add(id_token_cookie);
+    }
 
     Ok((claims, new_jar))
-}
\ No newline at end of file
+}
@@ -35,8 +35,9 @@ pub fn build_cors_layer() -> CorsLayer {
 
             CorsLayer::new()
                 .allow_origin(origins)
-                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
-                .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS])
+                .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, header::HeaderName::from_static("dpop")])
+                .expose_headers([header::WWW_AUTHENTICATE])
                 .allow_credentials(true)
         }
         None => {
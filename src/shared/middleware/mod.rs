@@ -5,5 +5,6 @@ pub mod cookies;
 pub mod layers;
 
 // Re-export commonly used items
-pub use auth::extractor::Claims;
+pub use auth::extractor::{AccessToken, Claims};
+pub use auth::step_up::{Mfa, RecentMfa, StepUp, StepUpPolicy, StepUpRequirement};
 pub use layers::apply_security_layers;
\ No newline at end of file
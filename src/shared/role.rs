@@ -1,7 +1,12 @@
+/// Whether `roles` contains `role`. The macros below reach it through the crate-root re-export.
+pub fn has_role(roles: &[String], role: &str) -> bool {
+    roles.iter().any(|r| r == role)
+}
+
 #[macro_export]
 macro_rules! require_role {
     ($claims:expr, $role:expr) => {{
-        if !$claims.roles.iter().any(|r| r == &$role) {
+        if !$crate::has_role(&$claims.roles, &$role) {
             return Err((
                 axum::http::StatusCode::FORBIDDEN,
                 format!("Missing required role: {}", $role),
@@ -14,9 +19,7 @@ macro_rules! require_role {
 macro_rules! require_any_role {
     ($claims:expr, [$( $role:expr ),+]) => {{
         let required_roles = [$( $role ),+];
-        if !required_roles.iter().any(|role| {
-            $claims.roles.iter().any(|r| r == role)
-        }) {
+        if !required_roles.iter().any(|role| $crate::has_role(&$claims.roles, role)) {
             return Err((
                 axum::http::StatusCode::FORBIDDEN,
                 format!("Missing any of required roles: {:?}", required_roles),